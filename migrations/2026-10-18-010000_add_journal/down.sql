@@ -0,0 +1,15 @@
+DROP TRIGGER journal_projects_insert;
+DROP TRIGGER journal_projects_update;
+DROP TRIGGER journal_projects_delete;
+DROP TRIGGER journal_tasks_insert;
+DROP TRIGGER journal_tasks_update;
+DROP TRIGGER journal_tasks_delete;
+DROP TRIGGER journal_subtasks_insert;
+DROP TRIGGER journal_subtasks_update;
+DROP TRIGGER journal_subtasks_delete;
+DROP TRIGGER journal_stretches_insert;
+DROP TRIGGER journal_stretches_update;
+DROP TRIGGER journal_stretches_delete;
+DROP INDEX idx_journal_operation;
+DROP TABLE journal;
+DROP TABLE operations;
\ No newline at end of file
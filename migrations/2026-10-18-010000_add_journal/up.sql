@@ -0,0 +1,39 @@
+CREATE TABLE operations(id INTEGER NOT NULL PRIMARY KEY, command TEXT NOT NULL, created INTEGER NOT NULL, state INTEGER NOT NULL DEFAULT 0);
+CREATE TABLE journal(id INTEGER NOT NULL PRIMARY KEY, operation_id INTEGER NOT NULL, table_name TEXT NOT NULL, action TEXT NOT NULL, row_id INTEGER NOT NULL, old_values TEXT, new_values TEXT, FOREIGN KEY(operation_id) REFERENCES operations(id) ON DELETE CASCADE);
+CREATE INDEX idx_journal_operation ON journal(operation_id);
+CREATE TRIGGER journal_projects_insert AFTER INSERT ON projects WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'projects', 'insert', NEW.id, NULL, json_object('id', NEW.id, 'code', NEW.code, 'directory', NEW.directory, 'name', NEW.name));
+END;
+CREATE TRIGGER journal_projects_update AFTER UPDATE ON projects WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'projects', 'update', NEW.id, json_object('id', OLD.id, 'code', OLD.code, 'directory', OLD.directory, 'name', OLD.name), json_object('id', NEW.id, 'code', NEW.code, 'directory', NEW.directory, 'name', NEW.name));
+END;
+CREATE TRIGGER journal_projects_delete AFTER DELETE ON projects WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'projects', 'delete', OLD.id, json_object('id', OLD.id, 'code', OLD.code, 'directory', OLD.directory, 'name', OLD.name), NULL);
+END;
+CREATE TRIGGER journal_tasks_insert AFTER INSERT ON tasks WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'tasks', 'insert', NEW.id, NULL, json_object('id', NEW.id, 'project_id', NEW.project_id, 'number', NEW.number, 'active_subtask', NEW.active_subtask));
+END;
+CREATE TRIGGER journal_tasks_update AFTER UPDATE ON tasks WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'tasks', 'update', NEW.id, json_object('id', OLD.id, 'project_id', OLD.project_id, 'number', OLD.number, 'active_subtask', OLD.active_subtask), json_object('id', NEW.id, 'project_id', NEW.project_id, 'number', NEW.number, 'active_subtask', NEW.active_subtask));
+END;
+CREATE TRIGGER journal_tasks_delete AFTER DELETE ON tasks WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'tasks', 'delete', OLD.id, json_object('id', OLD.id, 'project_id', OLD.project_id, 'number', OLD.number, 'active_subtask', OLD.active_subtask), NULL);
+END;
+CREATE TRIGGER journal_subtasks_insert AFTER INSERT ON subtasks WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'subtasks', 'insert', NEW.id, NULL, json_object('id', NEW.id, 'task_id', NEW.task_id, 'branch', NEW.branch, 'description', NEW.description, 'active', NEW.active, 'number', NEW.number));
+END;
+CREATE TRIGGER journal_subtasks_update AFTER UPDATE ON subtasks WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'subtasks', 'update', NEW.id, json_object('id', OLD.id, 'task_id', OLD.task_id, 'branch', OLD.branch, 'description', OLD.description, 'active', OLD.active, 'number', OLD.number), json_object('id', NEW.id, 'task_id', NEW.task_id, 'branch', NEW.branch, 'description', NEW.description, 'active', NEW.active, 'number', NEW.number));
+END;
+CREATE TRIGGER journal_subtasks_delete AFTER DELETE ON subtasks WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'subtasks', 'delete', OLD.id, json_object('id', OLD.id, 'task_id', OLD.task_id, 'branch', OLD.branch, 'description', OLD.description, 'active', OLD.active, 'number', OLD.number), NULL);
+END;
+CREATE TRIGGER journal_stretches_insert AFTER INSERT ON stretches WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'stretches', 'insert', NEW.id, NULL, json_object('id', NEW.id, 'subtask_id', NEW.subtask_id, 'start', NEW.start, 'end', NEW."end"));
+END;
+CREATE TRIGGER journal_stretches_update AFTER UPDATE ON stretches WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'stretches', 'update', NEW.id, json_object('id', OLD.id, 'subtask_id', OLD.subtask_id, 'start', OLD.start, 'end', OLD."end"), json_object('id', NEW.id, 'subtask_id', NEW.subtask_id, 'start', NEW.start, 'end', NEW."end"));
+END;
+CREATE TRIGGER journal_stretches_delete AFTER DELETE ON stretches WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'stretches', 'delete', OLD.id, json_object('id', OLD.id, 'subtask_id', OLD.subtask_id, 'start', OLD.start, 'end', OLD."end"), NULL);
+END;
\ No newline at end of file
@@ -0,0 +1,16 @@
+DROP TRIGGER journal_stretches_insert;
+DROP TRIGGER journal_stretches_update;
+DROP TRIGGER journal_stretches_delete;
+CREATE TABLE stretches_backup(id INTEGER NOT NULL PRIMARY KEY, subtask_id INTEGER NOT NULL, start INTEGER NOT NULL, end INTEGER, FOREIGN KEY(subtask_id) REFERENCES subtasks(id));
+INSERT INTO stretches_backup SELECT id, subtask_id, start, end FROM stretches;
+DROP TABLE stretches;
+ALTER TABLE stretches_backup RENAME TO stretches;
+CREATE TRIGGER journal_stretches_insert AFTER INSERT ON stretches WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'stretches', 'insert', NEW.id, NULL, json_object('id', NEW.id, 'subtask_id', NEW.subtask_id, 'start', NEW.start, 'end', NEW."end"));
+END;
+CREATE TRIGGER journal_stretches_update AFTER UPDATE ON stretches WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'stretches', 'update', NEW.id, json_object('id', OLD.id, 'subtask_id', OLD.subtask_id, 'start', OLD.start, 'end', OLD."end"), json_object('id', NEW.id, 'subtask_id', NEW.subtask_id, 'start', NEW.start, 'end', NEW."end"));
+END;
+CREATE TRIGGER journal_stretches_delete AFTER DELETE ON stretches WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'stretches', 'delete', OLD.id, json_object('id', OLD.id, 'subtask_id', OLD.subtask_id, 'start', OLD.start, 'end', OLD."end"), NULL);
+END;
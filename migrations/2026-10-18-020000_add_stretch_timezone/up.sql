@@ -0,0 +1,13 @@
+ALTER TABLE stretches ADD COLUMN timezone TEXT;
+DROP TRIGGER journal_stretches_insert;
+DROP TRIGGER journal_stretches_update;
+DROP TRIGGER journal_stretches_delete;
+CREATE TRIGGER journal_stretches_insert AFTER INSERT ON stretches WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'stretches', 'insert', NEW.id, NULL, json_object('id', NEW.id, 'subtask_id', NEW.subtask_id, 'start', NEW.start, 'end', NEW."end", 'timezone', NEW.timezone));
+END;
+CREATE TRIGGER journal_stretches_update AFTER UPDATE ON stretches WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'stretches', 'update', NEW.id, json_object('id', OLD.id, 'subtask_id', OLD.subtask_id, 'start', OLD.start, 'end', OLD."end", 'timezone', OLD.timezone), json_object('id', NEW.id, 'subtask_id', NEW.subtask_id, 'start', NEW.start, 'end', NEW."end", 'timezone', NEW.timezone));
+END;
+CREATE TRIGGER journal_stretches_delete AFTER DELETE ON stretches WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'stretches', 'delete', OLD.id, json_object('id', OLD.id, 'subtask_id', OLD.subtask_id, 'start', OLD.start, 'end', OLD."end", 'timezone', OLD.timezone), NULL);
+END;
@@ -0,0 +1,4 @@
+DROP TRIGGER journal_pauses_insert;
+DROP TRIGGER journal_pauses_update;
+DROP TRIGGER journal_pauses_delete;
+DROP TABLE pauses;
@@ -0,0 +1,10 @@
+CREATE TABLE pauses(id INTEGER NOT NULL PRIMARY KEY, subtask_id INTEGER NOT NULL, paused INTEGER NOT NULL, FOREIGN KEY(subtask_id) REFERENCES subtasks(id) ON DELETE CASCADE);
+CREATE TRIGGER journal_pauses_insert AFTER INSERT ON pauses WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'pauses', 'insert', NEW.id, NULL, json_object('id', NEW.id, 'subtask_id', NEW.subtask_id, 'paused', NEW.paused));
+END;
+CREATE TRIGGER journal_pauses_update AFTER UPDATE ON pauses WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'pauses', 'update', NEW.id, json_object('id', OLD.id, 'subtask_id', OLD.subtask_id, 'paused', OLD.paused), json_object('id', NEW.id, 'subtask_id', NEW.subtask_id, 'paused', NEW.paused));
+END;
+CREATE TRIGGER journal_pauses_delete AFTER DELETE ON pauses WHEN EXISTS (SELECT 1 FROM operations WHERE state = 0) BEGIN
+  INSERT INTO journal(operation_id, table_name, action, row_id, old_values, new_values) VALUES ((SELECT max(id) FROM operations WHERE state = 0), 'pauses', 'delete', OLD.id, json_object('id', OLD.id, 'subtask_id', OLD.subtask_id, 'paused', OLD.paused), NULL);
+END;
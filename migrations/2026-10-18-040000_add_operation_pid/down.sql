@@ -0,0 +1,6 @@
+CREATE TABLE operations_backup(id INTEGER NOT NULL PRIMARY KEY, command TEXT NOT NULL, created INTEGER NOT NULL, state INTEGER NOT NULL DEFAULT 0);
+INSERT INTO operations_backup SELECT id, command, created, state FROM operations;
+DROP TABLE operations;
+CREATE TABLE operations(id INTEGER NOT NULL PRIMARY KEY, command TEXT NOT NULL, created INTEGER NOT NULL, state INTEGER NOT NULL DEFAULT 0);
+INSERT INTO operations SELECT id, command, created, state FROM operations_backup;
+DROP TABLE operations_backup;
@@ -0,0 +1,16 @@
+function logtime {
+  local conclude result
+  conclude=$(command logtime __handshake) || return
+  command logtime --bash "$conclude" "$@"
+  result=$?
+  . "$conclude"
+  rm -f "$conclude"
+  return $result
+}
+__logtime_touch() {
+  command logtime touch
+}
+case ";$PROMPT_COMMAND;" in
+  *";__logtime_touch;"*) ;;
+  *) PROMPT_COMMAND="__logtime_touch${PROMPT_COMMAND:+;$PROMPT_COMMAND}" ;;
+esac
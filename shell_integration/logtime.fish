@@ -1,8 +1,11 @@
 function logtime
-  set conclude /tmp/(uuidgen)
-  env logtime --fish $conclude $argv
-  if test -f $conclude
-    . $conclude
-    rm $conclude
-  end
+  set conclude (command logtime __handshake); or return
+  command logtime --fish $conclude $argv
+  set result $status
+  . $conclude
+  rm -f $conclude
+  return $result
+end
+function __logtime_touch --on-event fish_prompt
+  command logtime touch
 end
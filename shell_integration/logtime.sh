@@ -0,0 +1,12 @@
+logtime() {
+  logtime_conclude=$(command logtime __handshake) || return
+  command logtime --sh "$logtime_conclude" "$@"
+  logtime_result=$?
+  . "$logtime_conclude"
+  rm -f "$logtime_conclude"
+  set -- "$logtime_result"
+  unset logtime_conclude logtime_result
+  return "$1"
+}
+# POSIX sh has no prompt hook; shells which expand PS1 can record activity
+# with PS1='$(command logtime touch)'"$PS1"
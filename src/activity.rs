@@ -0,0 +1,72 @@
+use chrono::Duration;
+use chrono::offset::TimeZone;
+use serde::{Serialize, Deserialize};
+use crate::config;
+use crate::logtimedb;
+use crate::models;
+use crate::status::Status;
+
+// When the shells were last used, as recorded by `logtime touch` from the
+// prompt hooks of the shell wrappers and before each command.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Activity {
+    pub last: Option<i64>,
+    // The latest gap in activity while a stretch was running, until it is
+    // dealt with by `logtime idle`
+    pub idle: Option<Idle>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Idle {
+    pub code: String,
+    pub from: i64,
+    pub until: i64,
+}
+
+impl Activity {
+    // A missing or damaged file only loses the activity history, so both
+    // start afresh rather than failing every prompt
+    pub fn load(path: &std::path::Path) -> Result<Self, String> {
+        match std::fs::read(path) {
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Activity::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+            Ok(contents) => Ok(serde_json::from_slice(&contents).unwrap_or_default()),
+        }
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> Result<(), String> {
+        let contents = serde_json::to_vec(self).map_err(|e| e.to_string())?;
+        logtimedb::write_file(path, contents.as_ref())
+    }
+}
+
+impl Idle {
+    pub fn describe(&self) -> String {
+        let tz = models::current_timezone();
+        format!("Idle for {} from {} while {} was running",
+                models::format_duration(Duration::seconds(self.until - self.from)),
+                tz.timestamp(self.from, 0).format(models::TIME_FORMAT),
+                self.code)
+    }
+}
+
+// Records activity at `now`, returning the gap since the previous activity
+// if a stretch was running throughout and it exceeds the idle threshold.
+// Only the cached status is consulted so that prompts stay fast.
+pub fn touch(db_path: &str, now: i64) -> Result<Option<Idle>, String> {
+    let path = logtimedb::activity_path(db_path);
+    let mut activity = Activity::load(path.as_ref())?;
+    let mut detected = None;
+    if let (Some(last), Ok(Some(status))) = (activity.last, Status::load(logtimedb::state_path(db_path).as_ref())) {
+        let from = last.max(status.started.timestamp());
+        let threshold = config::get().idle_threshold().unwrap_or_else(|_| Duration::minutes(15));
+        if now - from > threshold.num_seconds() {
+            let idle = Idle { code: status.code, from: from, until: now };
+            activity.idle = Some(idle.clone());
+            detected = Some(idle);
+        }
+    }
+    activity.last = Some(now);
+    activity.save(path.as_ref())?;
+    Ok(detected)
+}
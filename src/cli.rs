@@ -0,0 +1,364 @@
+use std::str::FromStr;
+
+use clap::{Arg, ArgAction, Command, ValueHint, value_parser};
+use chrono_tz::Tz;
+use crate::models;
+use crate::timeexpr::TimeExpr;
+
+pub fn app() -> Command {
+    public_app()
+        .subcommand(Command::new("touch")
+                    .about("Record shell activity, for prompt hooks")
+                    .hide(true))
+        .subcommand(Command::new("__handshake")
+                    .hide(true))
+        .subcommand(Command::new("__complete")
+                    .hide(true)
+                    .arg(Arg::new("words")
+                         .num_args(0..)
+                         .trailing_var_arg(true)
+                         .allow_hyphen_values(true)))
+}
+
+// The commands without those used only by the shell integration, for
+// generating completions
+pub fn public_app() -> Command {
+    Command::new("logtime")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Time recorder with git integration")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(Arg::new("db")
+             .long("db")
+             .value_name("FILE")
+             .value_hint(ValueHint::FilePath)
+             .global(true)
+             .help("SQLite database to use instead of ~/.logtime.sqlite"))
+        .arg(Arg::new("fish")
+             .long("fish")
+             .value_name("FILE")
+             .value_hint(ValueHint::FilePath)
+             .global(true)
+             .action(ArgAction::Append)
+             .help("Write fish commands for the calling shell to FILE"))
+        .arg(Arg::new("zsh")
+             .long("zsh")
+             .value_name("FILE")
+             .value_hint(ValueHint::FilePath)
+             .global(true)
+             .action(ArgAction::Append)
+             .help("Write zsh commands for the calling shell to FILE"))
+        .arg(Arg::new("bash")
+             .long("bash")
+             .value_name("FILE")
+             .value_hint(ValueHint::FilePath)
+             .global(true)
+             .action(ArgAction::Append)
+             .help("Write bash commands for the calling shell to FILE"))
+        .arg(Arg::new("sh")
+             .long("sh")
+             .value_name("FILE")
+             .value_hint(ValueHint::FilePath)
+             .global(true)
+             .action(ArgAction::Append)
+             .help("Write POSIX sh commands for the calling shell to FILE"))
+        .arg(Arg::new("nu")
+             .long("nu")
+             .value_name("FILE")
+             .value_hint(ValueHint::FilePath)
+             .global(true)
+             .action(ArgAction::Append)
+             .help("Write actions for the calling nushell to FILE"))
+        .arg(Arg::new("tz")
+             .long("tz")
+             .value_name("ZONE")
+             .global(true)
+             .value_parser(parse_tz)
+             .help("Timezone for entering and displaying times (overrides LOGTIME_TZ)"))
+        .subcommand(Command::new("current")
+                    .about("Print the task currently being timed"))
+        .subcommand(Command::new("status")
+                    .about("Show the current subtask, its stretch and today's total for its task")
+                    .arg(Arg::new("format")
+                         .long("format")
+                         .value_name("TEMPLATE")
+                         .help("Print TEMPLATE with {code}, {project}, {name}, {description}, {branch}, {started}, {elapsed} and {today} filled in ({{ and }} for literal braces), or nothing if no task is running")))
+        .subcommand(Command::new("prompt")
+                    .about("Print the current task for a shell prompt, without opening the database")
+                    .arg(Arg::new("format")
+                         .long("format")
+                         .value_name("TEMPLATE")
+                         .default_value("{code}")
+                         .help("Template as for `logtime status --format`")))
+        .subcommand(Command::new("start")
+                    .about("Stop any running stretch and start timing a subtask")
+                    .arg(code_arg()
+                         .value_parser(|s: &str| if s == "-" { Ok(String::from(s)) } else { parse_code(s) })
+                         .help("Subtask code: PROJECT-TASK[-SUBTASK], or - for the previous subtask"))
+                    .arg(time_arg("at")
+                         .long("at")
+                         .help("When work on the subtask began (default: now)")))
+        .subcommand(Command::new("stop")
+                    .about("Stop the running stretch")
+                    .arg(time_arg("time")
+                         .help("When the stretch ended (default: now)")))
+        .subcommand(Command::new("pause")
+                    .about("Stop the running stretch, remembering its subtask for `resume`")
+                    .arg(time_arg("time")
+                         .help("When the break began (default: now)")))
+        .subcommand(Command::new("resume")
+                    .about("Start timing the most recently paused subtask again"))
+        .subcommand(Command::new("cd")
+                    .about("Change to the directory of the current project"))
+        .subcommand(Command::new("display")
+                    .about("Show time spent per subtask")
+                    .arg(Arg::new("from")
+                         .value_name("DATE")
+                         .allow_hyphen_values(true)
+                         .value_parser(parse_date)
+                         .help("Show time from DATE until today (default: today)"))
+                    .arg(Arg::new("week")
+                         .long("week")
+                         .action(ArgAction::SetTrue)
+                         .conflicts_with_all(["from", "month", "range"])
+                         .help("Show time since the start of this week"))
+                    .arg(Arg::new("month")
+                         .long("month")
+                         .action(ArgAction::SetTrue)
+                         .conflicts_with_all(["from", "range"])
+                         .help("Show time since the start of this month"))
+                    .arg(Arg::new("range")
+                         .long("range")
+                         .value_name("FROM..TO")
+                         .allow_hyphen_values(true)
+                         .value_parser(parse_range)
+                         .conflicts_with("from")
+                         .help("Show time between two dates inclusive"))
+                    .arg(Arg::new("by")
+                         .long("by")
+                         .value_parser(["day", "project", "task", "subtask"])
+                         .default_value("day")
+                         .help("How to group totals"))
+                    .arg(zone_arg()))
+        .subcommand(Command::new("project")
+                    .about("Configure and inspect projects")
+                    .arg(Arg::new("code")
+                         .value_name("PROJECT")
+                         .value_hint(ValueHint::Other)
+                         .help("Project code"))
+                    .subcommand(Command::new("list")
+                                .about("List all projects"))
+                    .subcommand(Command::new("set-dir")
+                                .about("Set the project's working directory")
+                                .arg(Arg::new("path")
+                                     .value_hint(ValueHint::DirPath)
+                                     .help("Directory (default: the current directory)")))
+                    .subcommand(Command::new("set-name")
+                                .about("Set the project's name, or clear it if no name is given")
+                                .arg(Arg::new("name")
+                                     .num_args(0..)
+                                     .trailing_var_arg(true)))
+                    .subcommand(Command::new("show")
+                                .about("Show the project's settings")))
+        .subcommand(Command::new("subtask")
+                    .about("Configure and inspect subtasks")
+                    .arg(code_arg())
+                    .subcommand(Command::new("branch")
+                                .about("Set the git branch checked out when starting the subtask")
+                                .arg(Arg::new("name")
+                                     .required(true)
+                                     .help("Branch name"))
+                                .arg(Arg::new("new")
+                                     .long("new")
+                                     .value_name("SOURCE")
+                                     .num_args(0..=1)
+                                     .help("Also create the branch, optionally from SOURCE")))
+                    .subcommand(Command::new("describe")
+                                .about("Set the subtask's description, or clear it if none is given")
+                                .arg(Arg::new("text")
+                                     .num_args(0..)
+                                     .trailing_var_arg(true)))
+                    .subcommand(Command::new("show")
+                                .about("Show the subtask's settings")))
+        .subcommand(Command::new("add")
+                    .about("Record a stretch of time which has already finished")
+                    .arg(code_arg())
+                    .arg(time_arg("start")
+                         .required(true)
+                         .help("Start time"))
+                    .arg(time_arg("end")
+                         .required(true)
+                         .help("End time")))
+        .subcommand(Command::new("log")
+                    .about("List stretches with their ids")
+                    .arg(Arg::new("from")
+                         .value_name("DATE")
+                         .allow_hyphen_values(true)
+                         .value_parser(parse_date)
+                         .help("List stretches from DATE (default: today)"))
+                    .arg(zone_arg()))
+        .subcommand(Command::new("recent")
+                    .about("List the most recently started subtasks")
+                    .arg(Arg::new("count")
+                         .value_name("N")
+                         .value_parser(value_parser!(usize))
+                         .default_value("10")
+                         .help("How many subtasks to list")))
+        .subcommand(Command::new("edit")
+                    .about("Change an existing stretch")
+                    .arg(id_arg())
+                    .arg(time_arg("start")
+                         .long("start")
+                         .help("New start time"))
+                    .arg(time_arg("end")
+                         .long("end")
+                         .help("New end time"))
+                    .arg(Arg::new("code")
+                         .long("code")
+                         .value_name("CODE")
+                         .value_hint(ValueHint::Other)
+                         .value_parser(parse_code)
+                         .help("Move the stretch to another subtask")))
+        .subcommand(Command::new("split")
+                    .about("Split a stretch in two")
+                    .arg(id_arg())
+                    .arg(time_arg("time")
+                         .required(true)
+                         .help("Where to split the stretch")))
+        .subcommand(Command::new("rm")
+                    .about("Delete a stretch")
+                    .arg(id_arg()))
+        .subcommand(Command::new("export")
+                    .about("Write stretches or totals in a machine readable format")
+                    .arg(Arg::new("format")
+                         .long("format")
+                         .value_parser(["csv", "json", "jsonl", "ics"])
+                         .default_value("csv"))
+                    .arg(Arg::new("from")
+                         .long("from")
+                         .value_name("DATE")
+                         .allow_hyphen_values(true)
+                         .value_parser(parse_date))
+                    .arg(Arg::new("to")
+                         .long("to")
+                         .value_name("DATE")
+                         .allow_hyphen_values(true)
+                         .value_parser(parse_date))
+                    .arg(Arg::new("aggregate")
+                         .long("aggregate")
+                         .value_parser(["day", "task"])
+                         .help("Write totals instead of individual stretches"))
+                    .arg(zone_arg()))
+        .subcommand(Command::new("import")
+                    .about("Import stretches from an export or another time tracker")
+                    .arg(Arg::new("path")
+                         .required(true)
+                         .value_hint(ValueHint::AnyPath)
+                         .help("File to import (- for standard input), or timewarrior data directory"))
+                    .arg(Arg::new("format")
+                         .long("format")
+                         .value_parser(["csv", "json", "jsonl"])
+                         .help("Format of logtime exports (default: from the file extension)"))
+                    .arg(Arg::new("source")
+                         .long("source")
+                         .value_parser(["logtime", "timewarrior", "watson"])
+                         .default_value("logtime"))
+                    .arg(Arg::new("mapping")
+                         .long("mapping")
+                         .value_name("FILE")
+                         .value_hint(ValueHint::FilePath)
+                         .help("File of `name = CODE` lines mapping tags and projects to subtask codes"))
+                    .arg(Arg::new("dry-run")
+                         .long("dry-run")
+                         .action(ArgAction::SetTrue)
+                         .help("Report what would be imported without changing anything")))
+        .subcommand(Command::new("undo")
+                    .about("Revert the most recent change"))
+        .subcommand(Command::new("redo")
+                    .about("Reapply the most recently undone change"))
+        .subcommand(Command::new("completions")
+                    .about("Print a completion script for a shell")
+                    .arg(Arg::new("shell")
+                         .required(true)
+                         .value_parser(["fish", "zsh", "bash"])))
+        .subcommand(Command::new("idle")
+                    .about("Deal with idle time detected while a stretch was running")
+                    .arg(Arg::new("trim")
+                         .long("trim")
+                         .action(ArgAction::SetTrue)
+                         .conflicts_with_all(["split", "ignore"])
+                         .help("End the stretch at the last activity"))
+                    .arg(Arg::new("split")
+                         .long("split")
+                         .action(ArgAction::SetTrue)
+                         .conflicts_with("ignore")
+                         .help("Record the idle time against the configured idle_code"))
+                    .arg(Arg::new("ignore")
+                         .long("ignore")
+                         .action(ArgAction::SetTrue)
+                         .help("Keep the idle time in the stretch")))
+        .subcommand(Command::new("fix-open")
+                    .about("End stretches which have been left running for longer than max_open")
+                    .arg(time_arg("at")
+                         .long("at")
+                         .help("When the stretches should end"))
+                    .arg(Arg::new("last-activity")
+                         .long("last-activity")
+                         .action(ArgAction::SetTrue)
+                         .conflicts_with("at")
+                         .help("End the stretches at the last recorded shell activity")))
+}
+
+fn code_arg() -> Arg {
+    Arg::new("code")
+        .value_name("CODE")
+        .value_hint(ValueHint::Other)
+        .required(true)
+        .value_parser(parse_code)
+        .help("Subtask code: PROJECT-TASK[-SUBTASK]")
+}
+
+fn id_arg() -> Arg {
+    Arg::new("id")
+        .required(true)
+        .value_parser(value_parser!(i64))
+        .help("Stretch id, as shown by `logtime log`")
+}
+
+fn zone_arg() -> Arg {
+    Arg::new("zone")
+        .long("zone")
+        .value_name("ZONE")
+        .value_parser(|s: &str| s.parse::<models::Zone>())
+        .help("Show times in ZONE, or in the zone each stretch was recorded in with `recorded` (default: current zone)")
+}
+
+// Times accept the forms described in timeexpr, including `-15m`
+fn time_arg(name: &'static str) -> Arg {
+    Arg::new(name)
+        .value_name("TIME")
+        .allow_hyphen_values(true)
+        .value_parser(|s: &str| s.parse::<TimeExpr>())
+}
+
+fn parse_tz(s: &str) -> Result<String, String> {
+    Tz::from_str(s)
+        .map(|_| String::from(s))
+        .map_err(|err| err.to_string())
+}
+
+fn parse_code(s: &str) -> Result<String, String> {
+    s.parse::<models::SubtaskSpec>().map(|_| String::from(s))
+}
+
+fn parse_date(s: &str) -> Result<TimeExpr, String> {
+    s.parse::<TimeExpr>()
+}
+
+fn parse_range(s: &str) -> Result<(TimeExpr, TimeExpr), String> {
+    let mut parts = s.splitn(2, "..");
+    match (parts.next(), parts.next()) {
+        (Some(from), Some(until)) => Ok((parse_date(from)?, parse_date(until)?)),
+        _ => Err(String::from("expected FROM..TO")),
+    }
+}
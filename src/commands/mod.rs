@@ -1,71 +1,789 @@
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+
 use diesel::sqlite::SqliteConnection;
 use diesel::Connection;
+use chrono::{DateTime, Datelike};
+use chrono::offset::TimeZone;
+use chrono::naive::NaiveDate;
+use chrono_tz::Tz;
+use clap::ArgMatches;
 use crate::shell::Shell;
 use crate::models;
+use crate::journal;
+use crate::export;
+use crate::import;
+use crate::logtimedb;
+use crate::status::Status;
+use crate::timeexpr::TimeExpr;
+use crate::activity::{Activity, Idle};
+use crate::config;
+use crate::cli;
+use crate::completions;
 
-pub fn current<A: Iterator<Item=String>, S: Shell>(args: &mut A, conn: &SqliteConnection, shell: &mut S) {
+pub fn current<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
     match models::Task::current(conn) {
         None => { println!("No current task"); },
         Some(task) => { println!("{}", task.code(conn)) },
     }
+    Ok(())
 }
 
-pub fn stop<A: Iterator<Item=String>, S: Shell>(args: &mut A, conn: &SqliteConnection, shell: &mut S) {
-    models::Stretch::stop_all_at(conn, args.next())
-        .unwrap_or_else(|e| eprintln!("{}", e));
+pub fn status<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    warn_overlong(conn, None)?;
+    let status = Status::current(conn)?;
+    let now = models::now();
+    match (status, args.get_one::<String>("format")) {
+        (None, Some(_)) => {},
+        (None, None) => println!("No current task"),
+        (Some(status), Some(format)) => println!("{}", status.render(format.as_ref(), now)?),
+        (Some(status), None) => {
+            println!("Code: {}", status.code);
+            match status.name {
+                Some(ref name) => println!("Project: {} ({})", status.project, name),
+                None => println!("Project: {}", status.project),
+            }
+            println!("Description: {}", status.description.as_deref().unwrap_or("(none)"));
+            println!("Branch: {}", status.branch.as_deref().unwrap_or("(none)"));
+            println!("Started: {}", status.started.format(models::TIME_FORMAT));
+            println!("Elapsed: {}", models::format_duration(status.elapsed(now)));
+            println!("Today: {}", models::format_duration(status.today(now)));
+        },
+    }
+    Ok(())
 }
 
-pub fn start<A: Iterator<Item=String>, S: Shell>(args: &mut A, conn: &SqliteConnection, shell: &mut S) {
-    match args.next() {
-        Some(code) => {
-            SqliteConnection::transaction(conn, || {
-                models::Stretch::stop_all(conn)?;
-                let (project,_task,subtask) = models::Subtask
-                    ::for_code(conn, code.as_ref())?;
-                subtask.begin(conn)?;
-                project.directory.map(|d| shell.cd(std::path::Path::new(&d)))
-                    .transpose()
-                    .and_then(|cdr| cdr.and(subtask.branch)
-                              .map(|branch| shell.checkout(branch.as_ref()))
-                              .transpose())
-                    .map_err(|e| format!("{}", e))?;
-                Ok(())
-            }).map(|()| ())
+// Reads the state cached by the last command instead of the database, so
+// that prompts stay fast.
+pub fn prompt(args: &ArgMatches, db_path: &str) -> Result<(), models::DbOrMiscError> {
+    let state_path = logtimedb::state_path(db_path);
+    let status = match Status::load(state_path.as_ref()) {
+        Ok(status) => status,
+        Err(_) => {
+            let conn = logtimedb::open_without_migrations(db_path)?;
+            logtimedb::check_schema(&conn)?;
+            let status = Status::current(&conn)?;
+            Status::save(status.as_ref(), state_path.as_ref())?;
+            status
         },
-        None => { Err(models::DbOrMiscError::from("TO-DO")) },
-    }.unwrap_or_else(|e| eprintln!("{}", e));
+    };
+    if let Some(status) = status {
+        println!("{}", status.render(args.get_one::<String>("format").unwrap(), models::now())?);
+    }
+    Ok(())
 }
 
-pub fn cd<A: Iterator<Item=String>, S: Shell>(args: &mut A, conn: &SqliteConnection, shell: &mut S) {
+pub fn stop<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    models::Stretch::stop_all_at(conn, time_arg(args, "time")?)?;
+    for key in CONTEXT_VARIABLES {
+        shell.unset_env(key).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+const CONTEXT_VARIABLES: &[&str] = &["LOGTIME_PROJECT", "LOGTIME_TASK", "LOGTIME_SUBTASK", "LOGTIME_CODE", "LOGTIME_BRANCH"];
+
+fn export_context<S: Shell>(shell: &mut S, project: &models::Project, task: &models::Task, subtask: &models::Subtask) -> Result<(), std::io::Error> {
+    shell.env("LOGTIME_PROJECT", project.code.as_ref())?;
+    shell.env("LOGTIME_TASK", task.number.to_string().as_ref())?;
+    shell.env("LOGTIME_SUBTASK", subtask.number.to_string().as_ref())?;
+    shell.env("LOGTIME_CODE", models::subtask_code(project, task, subtask).as_ref())?;
+    match subtask.branch {
+        Some(ref branch) => shell.env("LOGTIME_BRANCH", branch.as_ref()),
+        None => shell.unset_env("LOGTIME_BRANCH"),
+    }
+}
+
+pub fn start<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    let code = args.get_one::<String>("code").unwrap();
+    let at = time_arg(args, "at")?;
+    if at.map_or(false, |at| at > models::now()) {
+        return Err(models::DbOrMiscError::from("Cannot start in the future"));
+    }
+    SqliteConnection::transaction(conn, || {
+        let (project,task,subtask) = if code == "-" {
+            models::recent_subtasks(conn, 2)?
+                .into_iter()
+                .nth(1)
+                .map(|(project, task, subtask, _)| (project, task, subtask))
+                .ok_or_else(|| models::DbOrMiscError::from("No previous subtask"))?
+        } else {
+            models::Subtask::for_code(conn, code.as_ref())?
+        };
+        let at = at.unwrap_or_else(models::now);
+        warn_overlong(conn, Some(at))?;
+        models::Stretch::stop_all_at(conn, Some(at))?;
+        subtask.begin_at(conn, at)?;
+        enter(shell, project, task, subtask)
+    })
+}
+
+// Point the shell at a subtask which has just been started
+fn enter<S: Shell>(shell: &mut S, project: models::Project, task: models::Task, subtask: models::Subtask) -> Result<(), models::DbOrMiscError> {
+    export_context(shell, &project, &task, &subtask)
+        .map_err(|e| format!("{}", e))?;
+    project.directory.map(|d| shell.cd(std::path::Path::new(&d)))
+        .transpose()
+        .and_then(|cdr| cdr.and(subtask.branch)
+                  .map(|branch| shell.checkout(branch.as_ref()))
+                  .transpose())
+        .map_err(|e| format!("{}", e))?;
+    Ok(())
+}
+
+pub fn pause<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    let at = time_arg(args, "time")?;
+    SqliteConnection::transaction(conn, || {
+        let stretch = models::Stretch::current(conn)
+            .ok_or_else(|| models::DbOrMiscError::from("No running stretch to pause"))?;
+        models::Stretch::stop_all_at(conn, at)?;
+        models::Subtask::load(conn, stretch.subtask_id)?
+            .pause(conn, at.unwrap_or_else(models::now))?;
+        for key in CONTEXT_VARIABLES {
+            shell.unset_env(key).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    })
+}
+
+pub fn resume<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    SqliteConnection::transaction(conn, || {
+        let (project, task, subtask) = models::Subtask::last_paused(conn)?
+            .ok_or_else(|| models::DbOrMiscError::from("Nothing is paused"))?;
+        models::Stretch::stop_all(conn)?;
+        subtask.begin(conn)?;
+        println!("Resumed {}", models::subtask_code(&project, &task, &subtask));
+        enter(shell, project, task, subtask)
+    })
+}
+
+pub fn cd<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
     match models::Project::current(conn) {
-        Err(diesel::result::Error::NotFound) => println!("No current task"),
-        Err(err) => eprintln!("{}", err),
+        Err(diesel::result::Error::NotFound) => { println!("No current task"); Ok(()) },
+        Err(err) => Err(models::DbOrMiscError::from(err)),
         Ok(project) => {
             match project.directory {
                 Some(dir) => shell.cd(std::path::Path::new(&dir))
-                    .map_or_else(|err| eprintln!("{}", err), |_| ()),
-                None => eprintln!("No directory set for current project")
+                    .map_err(|err| models::DbOrMiscError::from(err.to_string())),
+                None => Err(models::DbOrMiscError::from("No directory set for current project")),
             }
         },
     }
 }
 
-pub fn display<A: Iterator<Item=String>, S: Shell>(args: &mut A, conn: &SqliteConnection, shell: &mut S) {
-    let from = match args.next() {
-        None => models::today(),
-        Some(formatted) => chrono::naive::NaiveDate::parse_from_str(formatted.as_ref(), "%Y-%m-%d").unwrap(),
+pub fn display<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    warn_overlong(conn, None)?;
+    let today = models::today();
+    let (from, until) = if args.get_flag("week") {
+        (today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64), today)
+    } else if args.get_flag("month") {
+        (today.with_day(1).unwrap(), today)
+    } else if let Some((from, until)) = args.get_one::<(TimeExpr, TimeExpr)>("range") {
+        (from.date(models::now())?, until.date(models::now())?)
+    } else {
+        (date_arg(args, "from")?.unwrap_or(today), today)
+    };
+    let by = match args.get_one::<String>("by").map(|by| by.as_ref()) {
+        Some("project") => Grouping::Project,
+        Some("task") => Grouping::Task,
+        Some("subtask") => Grouping::Subtask,
+        _ => Grouping::Day,
     };
-    let time_hash = models::time_since(conn, from).unwrap();
-    let mut entries = time_hash.iter().collect::<Vec<_>>();
-    entries.sort_by(|(c1,_),(c2,_)| c1.cmp(c2));
-    for (date, entries) in entries {
-        println!("{}:", date.format("%Y-%m-%d"));
+    let time_hash = models::time_between(conn, from, until, zone(args))?;
+    let mut groups: BTreeMap<String, BTreeMap<String, chrono::Duration>> = BTreeMap::new();
+    for (date, entries) in time_hash.iter() {
         for (code, duration) in entries.iter() {
-            println!("  {}: {}:{}:{}", code,
-                     duration.num_hours(),
-                     duration.num_minutes() % 60,
-                     duration.num_seconds() & 60);
+            let group = match by {
+                Grouping::Day => date.format("%Y-%m-%d").to_string(),
+                Grouping::Project => code.splitn(2, '-').next().unwrap().to_owned(),
+                Grouping::Task => code.rsplitn(2, '-').nth(1).unwrap().to_owned(),
+                Grouping::Subtask => code.clone(),
+            };
+            groups.entry(group)
+                .or_insert_with(BTreeMap::new)
+                .entry(code.clone())
+                .and_modify(|d| { *d = *d + *duration })
+                .or_insert(*duration);
         }
     }
+    let mut total = chrono::Duration::zero();
+    for (group, entries) in groups {
+        let subtotal = entries.values().fold(chrono::Duration::zero(), |a, d| a + *d);
+        total = total + subtotal;
+        match by {
+            Grouping::Subtask => {
+                println!("{}: {}", group, models::format_duration(subtotal));
+            },
+            _ => {
+                println!("{}:", group);
+                for (code, duration) in entries.iter() {
+                    println!("  {}: {}", code, models::format_duration(*duration));
+                }
+                println!("  Subtotal: {}", models::format_duration(subtotal));
+            },
+        }
+    }
+    println!("Total: {}", models::format_duration(total));
+    Ok(())
+}
+
+enum Grouping {
+    Day,
+    Project,
+    Task,
+    Subtask,
 }
 
+pub fn project<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    let code = args.get_one::<String>("code");
+    match (args.subcommand(), code) {
+        (Some(("list", _)), _) | (None, None) => {
+            for project in models::Project::all(conn)? {
+                println!("{}\t{}\t{}", project.code,
+                         project.name.as_deref().unwrap_or(""),
+                         project.directory.as_deref().unwrap_or(""));
+            }
+            Ok(())
+        },
+        (Some(_), None) => Err(models::DbOrMiscError::from("A project code is required")),
+        (Some(("set-dir", sub)), Some(code)) => {
+            let path = match sub.get_one::<String>("path") {
+                Some(path) => std::path::PathBuf::from(path),
+                None => std::env::current_dir()
+                    .map_err(|err| err.to_string())?,
+            };
+            let dir = std::fs::canonicalize(&path)
+                .map_err(|err| format!("{}: {}", path.display(), err))?
+                .to_str()
+                .map(String::from)
+                .ok_or_else(|| models::DbOrMiscError::from("Directory path is not valid unicode"))?;
+            let mut project = models::get_project(conn, code.as_ref())?;
+            project.set_directory(conn, Some(dir))?;
+            Ok(())
+        },
+        (Some(("set-name", sub)), Some(code)) => {
+            let name = sub.get_many::<String>("name")
+                .map(|words| words.map(|word| word.as_ref()).collect::<Vec<&str>>().join(" "))
+                .filter(|name| !name.is_empty());
+            let mut project = models::get_project(conn, code.as_ref())?;
+            project.set_name(conn, name)?;
+            Ok(())
+        },
+        (_, Some(code)) => {
+            let project = match models::Project::by_code(conn, code.as_ref()) {
+                Err(diesel::result::Error::NotFound) => {
+                    return Err(models::DbOrMiscError::from(format!("No project with code {}", code)));
+                },
+                result => result?,
+            };
+            println!("Code: {}", project.code);
+            println!("Name: {}", project.name.as_deref().unwrap_or("(none)"));
+            println!("Directory: {}", project.directory.as_deref().unwrap_or("(none)"));
+            let mut numbers = project.tasks(conn)?.iter().map(|t| t.number).collect::<Vec<_>>();
+            numbers.sort();
+            println!("Tasks: {}", numbers.iter()
+                     .map(|n| n.to_string())
+                     .collect::<Vec<_>>()
+                     .join(", "));
+            Ok(())
+        },
+    }
+}
+
+fn branch_exists(dir: &str, branch: &str) -> bool {
+    std::process::Command::new("git")
+        .args(["-C", dir, "show-ref", "--verify", "--quiet"])
+        .arg(format!("refs/heads/{}", branch))
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+pub fn subtask<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    let code = args.get_one::<String>("code").unwrap();
+    match args.subcommand() {
+        Some(("branch", sub)) => {
+            let branch = sub.get_one::<String>("name").unwrap();
+            let create = if sub.contains_id("new") {
+                Some(sub.get_one::<String>("new"))
+            } else {
+                None
+            };
+            SqliteConnection::transaction(conn, || {
+                let (project,_task,mut subtask) = models::Subtask
+                    ::for_code(conn, code.as_ref())?;
+                subtask.set_branch(conn, Some(branch.clone()))?;
+                if let Some(source) = create {
+                    let dir = match project.directory {
+                        Some(dir) => dir,
+                        None => return Err(models::DbOrMiscError::from(
+                            format!("No directory set for project {}", project.code))),
+                    };
+                    if branch_exists(&dir, branch) {
+                        println!("Branch {} already exists", branch);
+                    } else {
+                        shell.cd(std::path::Path::new(&dir))
+                            .and_then(|_| shell.new_branch(branch.as_ref(), source.map(|s| s.as_ref())))
+                            .map_err(|e| format!("{}", e))?;
+                    }
+                }
+                Ok(())
+            })
+        },
+        Some(("describe", sub)) => {
+            let description = sub.get_many::<String>("text")
+                .map(|words| words.map(|word| word.as_ref()).collect::<Vec<&str>>().join(" "))
+                .filter(|description| !description.is_empty());
+            let (_,_,mut subtask) = models::Subtask::for_code(conn, code.as_ref())?;
+            subtask.set_description(conn, description)?;
+            Ok(())
+        },
+        _ => {
+            let (project,task,subtask) = models::Subtask::for_code(conn, code.as_ref())?;
+            println!("Code: {}", models::subtask_code(&project, &task, &subtask));
+            if let Some(name) = project.name {
+                println!("Project: {}", name);
+            }
+            println!("Description: {}", subtask.description.as_deref().unwrap_or("(none)"));
+            println!("Branch: {}", subtask.branch.as_deref().unwrap_or("(none)"));
+            Ok(())
+        },
+    }
+}
+
+pub fn add<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    let code = args.get_one::<String>("code").unwrap();
+    let start = time_arg(args, "start")?.unwrap();
+    let end = time_arg(args, "end")?.unwrap();
+    SqliteConnection::transaction(conn, || {
+        let (_project,_task,subtask) = models::Subtask
+            ::for_code(conn, code.as_ref())?;
+        subtask.add_stretch(conn, start, end)
+    })
+}
+
+pub fn log<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    let from = date_arg(args, "from")?.unwrap_or_else(models::today);
+    for (project, task, subtask, stretch) in models::stretches_between(conn, from, None, zone(args))? {
+        println!("{}\t{}\t{}\t{}", stretch.id,
+                 models::subtask_code(&project, &task, &subtask),
+                 stretch.span(),
+                 models::format_duration(stretch.duration()));
+    }
+    Ok(())
+}
+
+fn time_arg(args: &ArgMatches, name: &str) -> Result<Option<DateTime<Tz>>, models::DbOrMiscError> {
+    Ok(args.get_one::<TimeExpr>(name)
+       .map(|time| time.resolve(models::now()))
+       .transpose()?)
+}
+
+fn date_arg(args: &ArgMatches, name: &str) -> Result<Option<NaiveDate>, models::DbOrMiscError> {
+    Ok(args.get_one::<TimeExpr>(name)
+       .map(|time| time.date(models::now()))
+       .transpose()?)
+}
+
+fn zone(args: &ArgMatches) -> models::Zone {
+    args.get_one::<models::Zone>("zone").cloned()
+        .unwrap_or_else(|| models::Zone::Fixed(models::current_timezone()))
+}
+
+// Warn about running stretches which have probably been forgotten, given
+// the time they are about to be stopped at if any
+fn warn_overlong(conn: &SqliteConnection, stopping: Option<DateTime<Tz>>) -> Result<(), models::DbOrMiscError> {
+    let max = config::get().max_open().unwrap_or_else(|_| chrono::Duration::hours(12));
+    for (project, task, subtask, stretch) in models::overlong_stretches(conn, max)? {
+        let hint = match stopping {
+            Some(end) if end - stretch.start <= max => continue,
+            Some(end) => format!("it ends at {}, correct it with `logtime edit {} --end TIME`",
+                                 end.format(models::TIME_FORMAT), stretch.id),
+            None => String::from("cap it with `logtime fix-open`"),
+        };
+        eprintln!("Warning: stretch {} ({}) has been open since {}, longer than {}; {}",
+                  stretch.id,
+                  models::subtask_code(&project, &task, &subtask),
+                  stretch.start.format(models::TIME_FORMAT),
+                  models::format_duration(max),
+                  hint);
+    }
+    Ok(())
+}
+
+pub fn fix_open<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, db_path: &str, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    let max = config::get().max_open().unwrap_or_else(|_| chrono::Duration::hours(12));
+    let stretches = models::overlong_stretches(conn, max)?;
+    if stretches.is_empty() {
+        println!("No stretch has been open for longer than {}", models::format_duration(max));
+        return Ok(());
+    }
+    let path = logtimedb::activity_path(db_path);
+    let mut activity = Activity::load(path.as_ref())?;
+    let at = time_arg(args, "at")?;
+    if at.is_none() && !args.get_flag("last-activity") && !std::io::stdin().is_terminal() {
+        return Err(models::DbOrMiscError::from("Choose an end with --at TIME or --last-activity"));
+    }
+    let now = models::now();
+    let mut ends = Vec::new();
+    for (project, task, subtask, stretch) in stretches {
+        let code = models::subtask_code(&project, &task, &subtask);
+        // The start of the latest idle gap is the last activity seen while
+        // the stretch was running
+        let last = activity.idle.as_ref()
+            .map(|idle| now.timezone().timestamp(idle.from, 0))
+            .filter(|from| *from > stretch.start);
+        let end = match at {
+            Some(at) => Some(at),
+            None if args.get_flag("last-activity") => Some(last.ok_or_else(
+                    || format!("No activity recorded during stretch {} ({})", stretch.id, code))?),
+            None => ask_end(&stretch, code.as_ref(), last, now)?,
+        };
+        if let Some(end) = end {
+            if end > now {
+                return Err(models::DbOrMiscError::from("Cannot end a stretch in the future"));
+            }
+            ends.push((stretch, code, end));
+        }
+    }
+    SqliteConnection::transaction::<_, models::DbOrMiscError, _>(conn, || {
+        for (stretch, code, end) in ends.iter_mut() {
+            stretch.update(conn, stretch.subtask_id, stretch.start, Some(*end))?;
+            println!("Stretch {} ({}) now ends at {}", stretch.id, code, end.format(models::TIME_FORMAT));
+        }
+        if !ends.is_empty() {
+            for key in CONTEXT_VARIABLES {
+                shell.unset_env(key).map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    })?;
+    if !ends.is_empty() && activity.idle.is_some() {
+        activity.idle = None;
+        activity.save(path.as_ref())?;
+    }
+    Ok(())
+}
+
+fn ask_end(stretch: &models::Stretch, code: &str, last: Option<DateTime<Tz>>, now: DateTime<Tz>) -> Result<Option<DateTime<Tz>>, models::DbOrMiscError> {
+    println!("Stretch {} ({}) has been open since {}", stretch.id, code, stretch.start.format(models::TIME_FORMAT));
+    loop {
+        match last {
+            Some(last) => print!("End it at TIME, at the [l]ast activity ({}), or leave empty to skip: ", last.format(models::TIME_FORMAT)),
+            None => print!("End it at TIME, or leave empty to skip: "),
+        }
+        std::io::stdout().flush().map_err(|e| e.to_string())?;
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer).map_err(|e| e.to_string())? == 0 {
+            return Ok(None);
+        }
+        let end = match answer.trim() {
+            "" => return Ok(None),
+            "l" if last.is_some() => last.unwrap(),
+            answer => match answer.parse::<TimeExpr>().and_then(|time| time.resolve(now)) {
+                Ok(end) => end,
+                Err(err) => {
+                    println!("{}", err);
+                    continue;
+                },
+            },
+        };
+        if end <= stretch.start {
+            println!("The stretch started at {}", stretch.start.format(models::TIME_FORMAT));
+        } else if end > now {
+            println!("Cannot end a stretch in the future");
+        } else {
+            return Ok(Some(end));
+        }
+    }
+}
+
+enum IdleAction {
+    Trim,
+    Split,
+    Ignore,
+}
+
+pub fn idle<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, db_path: &str, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    let path = logtimedb::activity_path(db_path);
+    let mut activity = Activity::load(path.as_ref())?;
+    let idle = match activity.idle.clone() {
+        None => {
+            println!("No idle time recorded");
+            return Ok(());
+        },
+        Some(idle) => idle,
+    };
+    let action = if args.get_flag("trim") {
+        IdleAction::Trim
+    } else if args.get_flag("split") {
+        IdleAction::Split
+    } else if args.get_flag("ignore") {
+        IdleAction::Ignore
+    } else {
+        ask_idle_action(&idle)?
+    };
+    let tz = models::current_timezone();
+    let from = tz.timestamp(idle.from, 0);
+    match action {
+        IdleAction::Ignore => {},
+        IdleAction::Trim => SqliteConnection::transaction::<_, models::DbOrMiscError, _>(conn, || {
+            let (mut stretch, until) = idle_stretch(conn, &idle)?;
+            match stretch.end {
+                // Work after the user came back is kept
+                Some(end) if end > until => {
+                    if stretch.start < from {
+                        stretch.update(conn, stretch.subtask_id, stretch.start, Some(from))?;
+                        models::Subtask::load(conn, stretch.subtask_id)?
+                            .add_stretch(conn, until, end)?;
+                    } else {
+                        stretch.update(conn, stretch.subtask_id, until, Some(end))?;
+                    }
+                },
+                end => {
+                    if stretch.start < from {
+                        stretch.update(conn, stretch.subtask_id, stretch.start, Some(from))?;
+                    } else {
+                        stretch.delete(conn)?;
+                    }
+                    if end.is_none() {
+                        for key in CONTEXT_VARIABLES {
+                            shell.unset_env(key).map_err(|e| e.to_string())?;
+                        }
+                    }
+                },
+            }
+            Ok(())
+        })?,
+        IdleAction::Split => SqliteConnection::transaction::<_, models::DbOrMiscError, _>(conn, || {
+            let (mut stretch, until) = idle_stretch(conn, &idle)?;
+            let end = stretch.end;
+            let subtask = models::Subtask::load(conn, stretch.subtask_id)?;
+            let (_project, _task, idle_subtask) = models::Subtask::for_code(conn, config::get().idle_code())?;
+            if stretch.start < from {
+                stretch.update(conn, stretch.subtask_id, stretch.start, Some(from))?;
+                idle_subtask.add_stretch(conn, from, until)?;
+            } else {
+                stretch.update(conn, idle_subtask.id, stretch.start, Some(until))?;
+            }
+            match end {
+                None => subtask.begin_at(conn, until)?,
+                Some(end) if end > until => subtask.add_stretch(conn, until, end)?,
+                Some(_) => {},
+            }
+            Ok(())
+        })?,
+    }
+    activity.idle = None;
+    activity.save(path.as_ref())?;
+    Ok(())
+}
+
+// The stretch which was running during the idle time, and when the idle
+// time ends within it
+fn idle_stretch(conn: &SqliteConnection, idle: &Idle) -> Result<(models::Stretch, DateTime<Tz>), models::DbOrMiscError> {
+    let tz = models::current_timezone();
+    let from = tz.timestamp(idle.from, 0);
+    let stretch = models::Stretch::overlapping(conn, from, from + chrono::Duration::seconds(1), None)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| models::DbOrMiscError::from("The idle time is no longer part of a stretch; dismiss it with --ignore"))?;
+    let until = tz.timestamp(idle.until, 0);
+    let until = stretch.end.map_or(until, |end| end.min(until));
+    Ok((stretch, until))
+}
+
+fn ask_idle_action(idle: &Idle) -> Result<IdleAction, models::DbOrMiscError> {
+    if !std::io::stdin().is_terminal() {
+        return Err(models::DbOrMiscError::from(format!("{}; choose --trim, --split or --ignore", idle.describe())));
+    }
+    println!("{}", idle.describe());
+    loop {
+        print!("[t]rim the stretch, [s]plit the idle time into {} or [i]gnore it? ", config::get().idle_code());
+        std::io::stdout().flush().map_err(|e| e.to_string())?;
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer).map_err(|e| e.to_string())? == 0 {
+            return Err(models::DbOrMiscError::from("No action chosen"));
+        }
+        match answer.trim() {
+            "t" | "trim" => return Ok(IdleAction::Trim),
+            "s" | "split" => return Ok(IdleAction::Split),
+            "i" | "ignore" => return Ok(IdleAction::Ignore),
+            _ => {},
+        }
+    }
+}
+
+pub fn recent<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    let count = *args.get_one::<usize>("count").unwrap();
+    let now = models::now();
+    for (project, task, subtask, stretch) in models::recent_subtasks(conn, count)? {
+        let when = match stretch.end {
+            None => String::from("running"),
+            Some(end) => models::format_ago(now - end),
+        };
+        println!("{}\t{}", models::subtask_code(&project, &task, &subtask), when);
+    }
+    Ok(())
+}
+
+fn load_stretch(args: &ArgMatches, conn: &SqliteConnection) -> Result<models::Stretch, models::DbOrMiscError> {
+    let id = *args.get_one::<i64>("id").unwrap();
+    models::Stretch::load(conn, id)
+        .map_err(|e| match e {
+            diesel::result::Error::NotFound => models::DbOrMiscError::from(format!("No stretch with id {}", id)),
+            e => models::DbOrMiscError::from(e),
+        })
+}
+
+pub fn edit<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    SqliteConnection::transaction(conn, || {
+        let mut stretch = load_stretch(args, conn)?;
+        let start = time_arg(args, "start")?.unwrap_or(stretch.start);
+        let end = time_arg(args, "end")?.or(stretch.end);
+        let subtask_id = match args.get_one::<String>("code") {
+            Some(code) => models::Subtask::for_code(conn, code.as_ref())?.2.id,
+            None => stretch.subtask_id,
+        };
+        stretch.update(conn, subtask_id, start, end)
+    })
+}
+
+pub fn split<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    SqliteConnection::transaction(conn, || {
+        let mut stretch = load_stretch(args, conn)?;
+        let at = time_arg(args, "time")?.unwrap();
+        stretch.split(conn, at)
+    })
+}
+
+pub fn rm<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    load_stretch(args, conn)?
+        .delete(conn)
+        .map_err(models::DbOrMiscError::from)
+}
+
+pub fn undo<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    match journal::undo(conn)? {
+        None => println!("Nothing to undo"),
+        Some(operation) => println!("Undid: {}", operation.command),
+    }
+    Ok(())
+}
+
+pub fn redo<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    match journal::redo(conn)? {
+        None => println!("Nothing to redo"),
+        Some(operation) => println!("Redid: {}", operation.command),
+    }
+    Ok(())
+}
+
+pub fn export<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    let format = args.get_one::<String>("format").unwrap().parse::<export::Format>()?;
+    let from = date_arg(args, "from")?
+        .unwrap_or_else(|| NaiveDate::from_ymd(1970, 1, 1));
+    let until = date_arg(args, "to")?;
+    let aggregate = args.get_one::<String>("aggregate")
+        .map(|aggregate| aggregate.parse::<export::Aggregate>())
+        .transpose()?;
+    let stdout = std::io::stdout();
+    let output = stdout.lock();
+    let until_today = until.unwrap_or_else(models::today);
+    let zone = zone(args);
+    match aggregate {
+        None if format == export::Format::Ics => export::write_ics(conn, from, until, zone, output),
+        None => export::stretch_records(conn, from, until, zone)
+            .and_then(|records| export::write_records(&records, format, output).map_err(From::from)),
+        Some(export::Aggregate::Day) => export::day_records(conn, from, until_today, zone)
+            .and_then(|records| export::write_records(&records, format, output).map_err(From::from)),
+        Some(export::Aggregate::Task) => export::task_records(conn, from, until_today, zone)
+            .and_then(|records| export::write_records(&records, format, output).map_err(From::from)),
+    }
+}
+
+pub fn import<S: Shell>(args: &ArgMatches, conn: &SqliteConnection, shell: &mut S) -> Result<(), models::DbOrMiscError> {
+    let path = args.get_one::<String>("path").unwrap();
+    let dry_run = args.get_flag("dry-run");
+    let mapping = match args.get_one::<String>("mapping") {
+        None => import::Mapping::empty(),
+        Some(mapping_path) => std::fs::File::open(&mapping_path)
+            .map_err(|e| e.to_string())
+            .and_then(import::Mapping::load)
+            .map_err(|e| format!("Failed to read {}: {}", mapping_path, e))?,
+    };
+    let open = |path: &str| std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path, e));
+    let (entries, unmapped) = match args.get_one::<String>("source").unwrap().as_ref() {
+        "timewarrior" => import::timewarrior::read_path(std::path::Path::new(&path), &mapping),
+        "watson" => open(path.as_ref())
+            .and_then(|file| import::watson::read(file, &mapping)),
+        _ => args.get_one::<String>("format")
+            .map(|format| format.parse::<export::Format>())
+            .transpose()?
+            .or_else(|| import::format_for_path(path.as_ref()))
+            .ok_or_else(|| format!("Cannot tell the format of {}; use --format", path))
+            .and_then(|format| if path == "-" {
+                import::read_records(std::io::stdin(), format)
+            } else {
+                open(path.as_ref()).and_then(|file| import::read_records(file, format))
+            })
+            .map(|entries| (entries, Vec::new())),
+    }?;
+    let mut summary = import::import(conn, entries, dry_run)?;
+    summary.skipped.extend(unmapped);
+    print_import_summary(&summary, dry_run);
+    Ok(())
+}
+
+// Prints the codes or projects the last of the words of a command line
+// could be, for the completion scripts. This runs on every Tab press, so
+// nothing is written, not even a new database.
+pub fn complete(args: &ArgMatches, db_path: &str) -> Result<(), models::DbOrMiscError> {
+    let words = args.get_many::<String>("words").into_iter().flatten().cloned().collect::<Vec<_>>();
+    let (value, db) = completions::dynamic(cli::public_app(), &words);
+    let db_path = match db {
+        Some(ref path) if path.starts_with("~/") => format!("{}{}", std::env::var("HOME").unwrap_or_default(), &path[1..]),
+        Some(path) => path,
+        None => String::from(db_path),
+    };
+    if value.is_none() || !std::path::Path::new(&db_path).exists() {
+        return Ok(());
+    }
+    let conn = logtimedb::open_without_migrations(&db_path)?;
+    logtimedb::check_schema(&conn)?;
+    let codes = match value {
+        Some(completions::Value::Projects) => models::Project::all(&conn)?
+            .into_iter()
+            .map(|project| project.code)
+            .collect(),
+        _ => models::all_codes(&conn)?,
+    };
+    for code in codes {
+        println!("{}", code);
+    }
+    Ok(())
+}
+
+fn print_import_summary(summary: &import::Summary, dry_run: bool) {
+    if dry_run {
+        println!("Would import {} stretches", summary.imported);
+    } else {
+        println!("Imported {} stretches", summary.imported);
+    }
+    if !summary.new_projects.is_empty() {
+        println!("New projects: {}", summary.new_projects.join(", "));
+    }
+    if !summary.conflicts.is_empty() {
+        println!("Conflicts:");
+        for conflict in summary.conflicts.iter() {
+            println!("  {}", conflict);
+        }
+    }
+    if !summary.skipped.is_empty() {
+        println!("Skipped:");
+        for skipped in summary.skipped.iter() {
+            println!("  {}", skipped);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::io::Write;
+
+use clap::{Arg, Command};
+use clap_complete::Shell;
+
+// The command tree is completed by clap_complete. Each script then gets a
+// hook which passes the command line to `logtime __complete`, which prints
+// the codes or projects the word being completed could be, if any.
+
+const FISH_HOOK: &str = r#"
+function __fish_logtime_values
+    command logtime __complete -- (commandline -opc) (commandline -ct | string collect --allow-empty) 2>/dev/null
+end
+
+complete -c logtime -n 'test (count (__fish_logtime_values)) -gt 0' -f -a '(__fish_logtime_values)'
+"#;
+
+const BASH_HOOK: &str = r#"
+_logtime_values() {
+    local cur="$2" values word
+    _logtime "$@"
+    values=$(command logtime __complete -- "${COMP_WORDS[@]:0:COMP_CWORD+1}" 2>/dev/null)
+    if [[ -n $values ]]; then
+        local static=("${COMPREPLY[@]}")
+        COMPREPLY=($(compgen -W "$values" -- "$cur"))
+        for word in "${static[@]}"; do
+            [[ $word == "<"* || $word == "$cur" ]] || COMPREPLY+=("$word")
+        done
+        compopt +o nospace 2>/dev/null
+    fi
+}
+complete -F _logtime_values -o bashdefault -o default logtime
+"#;
+
+// Replaces the end of clap_complete's zsh script, so that the hook is also
+// used when the script is autoloaded as _logtime
+const ZSH_TAIL: &str = r#"if [ "$funcstack[1]" = "_logtime" ]; then
+    _logtime "$@"
+else
+    compdef _logtime logtime
+fi
+"#;
+
+const ZSH_HOOK: &str = r#"_logtime_values() {
+    local -a values
+    _logtime "$@"
+    values=(${(f)"$(command logtime __complete -- "${(@)words[1,CURRENT]}" 2>/dev/null)"})
+    (( $#values )) && compadd -a values
+}
+
+compdef _logtime_values logtime
+if [ "$funcstack[1]" = "_logtime" ]; then
+    _logtime_values "$@"
+fi
+"#;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Codes,
+    Projects,
+}
+
+pub fn generate<W: Write>(shell: &str, mut app: Command, output: &mut W) -> std::io::Result<()> {
+    let generator = match shell {
+        "fish" => Shell::Fish,
+        "zsh" => Shell::Zsh,
+        "bash" => Shell::Bash,
+        _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Unknown shell: {}", shell))),
+    };
+    let mut script = Vec::new();
+    clap_complete::generate(generator, &mut app, "logtime", &mut script);
+    let script = String::from_utf8(script)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    match generator {
+        Shell::Fish => write!(output, "{}{}", script, FISH_HOOK),
+        Shell::Bash => write!(output, "{}{}", script, BASH_HOOK),
+        _ => match script.strip_suffix(ZSH_TAIL) {
+            Some(script) => write!(output, "{}{}", script, ZSH_HOOK),
+            None => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Unexpected end of the zsh completion script")),
+        },
+    }
+}
+
+// Finds what the last of `words`, a command line up to the word being
+// completed, is a value of, along with the database given by --db if any.
+pub fn dynamic(app: Command, words: &[String]) -> (Option<Value>, Option<String>) {
+    let mut app = app;
+    app.build();
+    let mut cmd = &app;
+    let mut db = None;
+    let mut pending: Option<&Arg> = None;
+    let mut positionals = 0;
+    let mut only_positionals = false;
+    let (current, before) = match words.split_last() {
+        Some((current, before)) => (current, before.iter().skip(1)),
+        None => return (None, None),
+    };
+    for word in before {
+        if let Some(arg) = pending.take() {
+            if arg.get_id() == "db" {
+                db = Some(word.clone());
+            }
+            continue;
+        }
+        if !only_positionals && word == "--" {
+            only_positionals = true;
+        } else if !only_positionals && word.starts_with("--") {
+            let (name, value) = match word[2..].find('=') {
+                Some(i) => (&word[2..2 + i], Some(&word[3 + i..])),
+                None => (&word[2..], None),
+            };
+            let arg = cmd.get_arguments().find(|arg| arg.get_long() == Some(name));
+            match (arg, value) {
+                (Some(arg), Some(value)) if arg.get_id() == "db" => db = Some(String::from(value)),
+                (Some(arg), None) if arg.get_action().takes_values() => pending = Some(arg),
+                _ => {},
+            }
+        } else if !only_positionals && word.starts_with('-') && word.len() > 1 {
+            let last = word.chars().last().unwrap();
+            pending = cmd.get_arguments()
+                .find(|arg| arg.get_short() == Some(last) && arg.get_action().takes_values());
+        } else if let Some(sub) = cmd.find_subcommand(word).filter(|_| !only_positionals) {
+            cmd = sub;
+            positionals = 0;
+        } else {
+            positionals += 1;
+        }
+    }
+    let arg = match pending {
+        Some(arg) => Some(arg),
+        None if current.starts_with('-') && !only_positionals => None,
+        None => cmd.get_positionals().nth(positionals),
+    };
+    let value = arg
+        .and_then(|arg| arg.get_value_names())
+        .and_then(|names| names.first())
+        .and_then(|name| match name.as_str() {
+            "CODE" => Some(Value::Codes),
+            "PROJECT" => Some(Value::Projects),
+            _ => None,
+        });
+    (value, db)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use crate::cli;
+    use super::{dynamic, Value};
+
+    fn complete(line: &str) -> (Option<Value>, Option<String>) {
+        let mut words = line.split(' ').map(String::from).collect::<Vec<_>>();
+        words.insert(0, String::from("logtime"));
+        dynamic(cli::app(), &words)
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(complete("start "), (Some(Value::Codes), None));
+        assert_eq!(complete("start ab-1"), (Some(Value::Codes), None));
+        assert_eq!(complete("start --at 9:00 ab"), (Some(Value::Codes), None));
+        assert_eq!(complete("start ab-1-1 "), (None, None));
+        assert_eq!(complete("start --"), (None, None));
+        assert_eq!(complete("start --at "), (None, None));
+        assert_eq!(complete("add "), (Some(Value::Codes), None));
+        assert_eq!(complete("edit 3 --code "), (Some(Value::Codes), None));
+        assert_eq!(complete("edit "), (None, None));
+        assert_eq!(complete("project "), (Some(Value::Projects), None));
+        assert_eq!(complete("subtask ab-1-1 branch "), (None, None));
+        assert_eq!(complete(""), (None, None));
+    }
+
+    #[test]
+    fn database() {
+        assert_eq!(complete("--db /tmp/x.sqlite start "), (Some(Value::Codes), Some(String::from("/tmp/x.sqlite"))));
+        assert_eq!(complete("start --db=/tmp/y.sqlite "), (Some(Value::Codes), Some(String::from("/tmp/y.sqlite"))));
+        assert_eq!(complete("--tz UTC project "), (Some(Value::Projects), None));
+    }
+
+    fn generated(shell: &str) -> String {
+        let mut output = Vec::new();
+        super::generate(shell, cli::public_app(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    // Runs the bash completion for `line` with a stub logtime on the PATH
+    // which answers `__complete` with the words it was given
+    fn bash_complete(line: &str) -> Vec<String> {
+        let dir = std::env::temp_dir().join(format!("logtime-completion-test-{}-{}", std::process::id(), line.len()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let stub = dir.join("logtime");
+        std::fs::write(&stub, "#!/bin/sh\n[ \"$1\" = __complete ] || exit 1\nshift 2\ncase \"$*\" in *--db*) echo other-1-1 ;; *) printf 'ab-1-1\\nab-2-1\\nxy-1-1\\n' ;; esac\n").unwrap();
+        std::process::Command::new("chmod").arg("+x").arg(&stub).status().unwrap();
+        std::fs::write(dir.join("completion.bash"), generated("bash")).unwrap();
+        let output = Command::new("bash")
+            .arg("-c")
+            .arg("source \"$2\"; read -ra COMP_WORDS <<< \"$1\"; [[ $1 == *' ' ]] && COMP_WORDS+=(''); COMP_CWORD=$((${#COMP_WORDS[@]} - 1)); _logtime_values logtime \"${COMP_WORDS[COMP_CWORD]}\" \"${COMP_WORDS[COMP_CWORD-1]}\"; printf '%s\\n' \"${COMPREPLY[@]}\"")
+            .arg("bash")
+            .arg(format!("logtime {}", line))
+            .arg(dir.join("completion.bash"))
+            .env("PATH", format!("{}:{}", dir.display(), std::env::var("PATH").unwrap()))
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let mut words = String::from_utf8(output.stdout).unwrap().lines().filter(|line| !line.is_empty()).map(String::from).collect::<Vec<_>>();
+        words.sort();
+        words
+    }
+
+    #[test]
+    fn bash_completes_subcommands_and_codes() {
+        assert!(bash_complete("st").iter().eq(["start", "status", "stop"].iter()));
+        assert!(!bash_complete("").iter().any(|word| word == "touch" || word.starts_with("__")));
+        assert_eq!(bash_complete("start ab"), vec!["ab-1-1", "ab-2-1"]);
+        assert_eq!(bash_complete("--db other.sqlite start o"), vec!["other-1-1"]);
+        assert!(bash_complete("start ").iter().any(|word| word == "xy-1-1"));
+        assert_eq!(bash_complete("edit 3 --code x"), vec!["xy-1-1"]);
+        assert_eq!(bash_complete("start --a"), vec!["--at"]);
+    }
+
+    #[test]
+    fn zsh_and_fish_scripts_call_back() {
+        let zsh = generated("zsh");
+        assert!(zsh.ends_with(super::ZSH_HOOK));
+        assert!(zsh.contains("_logtime() {"));
+        let fish = generated("fish");
+        assert!(fish.ends_with(super::FISH_HOOK));
+        assert!(!fish.contains("\"touch\""));
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use chrono::Duration;
+use chrono_tz::Tz;
+use crate::models;
+use crate::timeexpr;
+
+// Settings from ~/.config/logtime/config (or $LOGTIME_CONFIG), one
+// `key = value` per line with # comments.
+pub struct Config {
+    settings: HashMap<String, String>,
+}
+
+const KEYS: &[&str] = &["timezone", "idle_threshold", "idle_code", "max_open"];
+
+impl Config {
+    pub fn load<R: Read>(input: R) -> Result<Self, String> {
+        let mut settings = HashMap::new();
+        for (number, line) in BufReader::new(input).lines().enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) if KEYS.contains(&key.trim()) => {
+                    settings.insert(key.trim().to_owned(), value.trim().to_owned());
+                },
+                (Some(key), Some(_)) => return Err(format!("line {}: unknown setting {:?}", number + 1, key.trim())),
+                _ => return Err(format!("line {}: expected <setting> = <value>", number + 1)),
+            }
+        }
+        let config = Config { settings: settings };
+        config.timezone().map_err(|e| format!("timezone: {}", e))?;
+        config.idle_threshold().map_err(|e| format!("idle_threshold: {}", e))?;
+        config.idle_code().parse::<models::SubtaskSpec>().map_err(|e| format!("idle_code: {}", e))?;
+        config.max_open().map_err(|e| format!("max_open: {}", e))?;
+        Ok(config)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.settings.get(key).map(|value| value.as_ref())
+    }
+
+    pub fn timezone(&self) -> Result<Option<Tz>, String> {
+        self.get("timezone")
+            .map(|zone| Tz::from_str(zone).map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    // How long the shells may be inactive before a running stretch is
+    // considered idle, such as 15m or 1h30m
+    pub fn idle_threshold(&self) -> Result<Duration, String> {
+        match self.get("idle_threshold") {
+            None => Ok(Duration::minutes(15)),
+            Some(threshold) => timeexpr::parse_duration(threshold)
+                .ok_or_else(|| format!("expected a duration such as 15m, not {:?}", threshold)),
+        }
+    }
+
+    // How long a stretch may stay open before it is assumed to be forgotten
+    pub fn max_open(&self) -> Result<Duration, String> {
+        match self.get("max_open") {
+            None => Ok(Duration::hours(12)),
+            Some(max) => timeexpr::parse_duration(max)
+                .ok_or_else(|| format!("expected a duration such as 12h, not {:?}", max)),
+        }
+    }
+
+    // Where idle time split out of a stretch is recorded
+    pub fn idle_code(&self) -> &str {
+        self.get("idle_code").unwrap_or("idle-1")
+    }
+}
+
+pub fn path() -> Option<std::path::PathBuf> {
+    if let Some(path) = std::env::var_os("LOGTIME_CONFIG") {
+        return Some(std::path::PathBuf::from(path));
+    }
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| std::path::PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("logtime").join("config"))
+}
+
+pub fn get() -> &'static Config {
+    static CONFIG: OnceLock<Config> = OnceLock::new();
+    CONFIG.get_or_init(|| {
+        let path = match path() {
+            Some(path) => path,
+            None => return Config { settings: HashMap::new() },
+        };
+        match std::fs::File::open(&path) {
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Config { settings: HashMap::new() },
+            file => file.map_err(|e| e.to_string())
+                .and_then(Config::load)
+                .unwrap_or_else(|e| {
+                    eprintln!("Ignoring {}: {}", path.display(), e);
+                    Config { settings: HashMap::new() }
+                }),
+        }
+    })
+}
+
+// The zone used when LOGTIME_TZ is not set: the configured one, or else the
+// system's, or else UTC.
+pub fn default_timezone() -> Tz {
+    static ZONE: OnceLock<Tz> = OnceLock::new();
+    *ZONE.get_or_init(|| {
+        get().timezone().ok().flatten()
+            .or_else(|| iana_time_zone::get_timezone().ok()
+                     .and_then(|zone| Tz::from_str(zone.as_ref()).ok()))
+            .unwrap_or(Tz::UTC)
+    })
+}
@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use chrono::{Duration, FixedOffset, Offset, TimeZone};
+use chrono::naive::{NaiveDate, NaiveDateTime};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+use diesel::sqlite::SqliteConnection;
+use serde::{Serialize, Deserialize};
+use crate::models;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    Json,
+    Jsonl,
+    Ics,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Aggregate {
+    Day,
+    Task,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StretchRecord {
+    pub project: String,
+    pub task: i64,
+    pub subtask: i64,
+    pub start: String,
+    pub end: Option<String>,
+    pub duration: i64,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    pub branch: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DayRecord {
+    pub date: String,
+    pub code: String,
+    pub duration: i64,
+}
+
+#[derive(Serialize)]
+pub struct TaskRecord {
+    pub code: String,
+    pub duration: i64,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            "jsonl" => Ok(Format::Jsonl),
+            "ics" => Ok(Format::Ics),
+            _ => Err(format!("Unknown format: {}", s)),
+        }
+    }
+}
+
+impl std::str::FromStr for Aggregate {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(Aggregate::Day),
+            "task" => Ok(Aggregate::Task),
+            _ => Err(format!("Unknown aggregation: {}", s)),
+        }
+    }
+}
+
+pub fn stretch_records(conn: &SqliteConnection, from: NaiveDate, until: Option<NaiveDate>, zone: models::Zone) -> Result<Vec<StretchRecord>, models::DbOrMiscError> {
+    Ok(models::stretches_between(conn, from, until, zone)?
+        .into_iter()
+        .map(|(project, task, subtask, stretch)| StretchRecord {
+            project: project.code,
+            task: task.number,
+            subtask: subtask.number,
+            start: stretch.start.to_rfc3339(),
+            end: stretch.end.map(|end| end.to_rfc3339()),
+            duration: stretch.duration().num_seconds(),
+            timezone: stretch.timezone.map(|tz| tz.name().to_owned()),
+            branch: subtask.branch,
+            description: subtask.description,
+        })
+        .collect())
+}
+
+pub fn day_records(conn: &SqliteConnection, from: NaiveDate, until: NaiveDate, zone: models::Zone) -> Result<Vec<DayRecord>, models::DbOrMiscError> {
+    let mut records = models::time_between(conn, from, until, zone)?
+        .into_iter()
+        .flat_map(|(date, entries)| entries.into_iter()
+                  .map(move |(code, duration)| DayRecord {
+                      date: date.format("%Y-%m-%d").to_string(),
+                      code: code,
+                      duration: duration.num_seconds(),
+                  }))
+        .collect::<Vec<_>>();
+    records.sort_by(|a, b| (&a.date, &a.code).cmp(&(&b.date, &b.code)));
+    Ok(records)
+}
+
+pub fn task_records(conn: &SqliteConnection, from: NaiveDate, until: NaiveDate, zone: models::Zone) -> Result<Vec<TaskRecord>, models::DbOrMiscError> {
+    let mut totals = BTreeMap::new();
+    for (_, entries) in models::time_between(conn, from, until, zone)? {
+        for (code, duration) in entries {
+            let task_code = code.rsplitn(2, '-').nth(1).unwrap().to_owned();
+            *totals.entry(task_code).or_insert(0) += duration.num_seconds();
+        }
+    }
+    Ok(totals.into_iter()
+       .map(|(code, duration)| TaskRecord { code: code, duration: duration })
+       .collect())
+}
+
+pub fn write_records<R: Serialize, W: Write>(records: &[R], format: Format, mut output: W) -> Result<(), String> {
+    match format {
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(output);
+            for record in records {
+                writer.serialize(record).map_err(|e| e.to_string())?;
+            }
+            writer.flush().map_err(|e| e.to_string())
+        },
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut output, records).map_err(|e| e.to_string())?;
+            writeln!(output).map_err(|e| e.to_string())
+        },
+        Format::Jsonl => {
+            for record in records {
+                serde_json::to_writer(&mut output, record).map_err(|e| e.to_string())?;
+                writeln!(output).map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        },
+        Format::Ics => Err(String::from("ics output is only available for unaggregated stretches")),
+    }
+}
+
+pub fn write_ics<W: Write>(conn: &SqliteConnection, from: NaiveDate, until: Option<NaiveDate>, zone: models::Zone, mut output: W) -> Result<(), models::DbOrMiscError> {
+    let tz = match zone {
+        models::Zone::Fixed(tz) => tz,
+        models::Zone::Recorded => models::current_timezone(),
+    };
+    // Running stretches have no end yet, so they are left out
+    let stretches = models::stretches_between(conn, from, until, zone)?
+        .into_iter()
+        .filter(|(_, _, _, stretch)| stretch.end.is_some())
+        .collect::<Vec<_>>();
+    let mut zones = BTreeMap::new();
+    for (_, _, _, stretch) in stretches.iter() {
+        let start = stretch.start.naive_utc();
+        let end = stretch.end.unwrap().naive_utc();
+        let range = zones.entry(stretch.start.timezone().name())
+            .or_insert((stretch.start.timezone(), start, end));
+        range.1 = range.1.min(start);
+        range.2 = range.2.max(end);
+    }
+    let stamp = chrono::offset::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        String::from("PRODID:-//logtime//logtime//EN"),
+        format!("X-WR-TIMEZONE:{}", tz.name()),
+    ];
+    for (zone, from, until) in zones.values() {
+        lines.extend(vtimezone(*zone, from.date().and_hms(0, 0, 0), *until));
+    }
+    for (project, task, subtask, stretch) in stretches {
+        let zone = stretch.start.timezone();
+        let end = stretch.end.unwrap().with_timezone(&zone);
+        let summary = match subtask.description {
+            Some(ref description) => format!("{} {}", models::subtask_code(&project, &task, &subtask), description),
+            None => models::subtask_code(&project, &task, &subtask),
+        };
+        lines.push(String::from("BEGIN:VEVENT"));
+        lines.push(format!("UID:stretch-{}@logtime", stretch.id));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART;TZID={}:{}", zone.name(), stretch.start.format("%Y%m%dT%H%M%S")));
+        lines.push(format!("DTEND;TZID={}:{}", zone.name(), end.format("%Y%m%dT%H%M%S")));
+        lines.push(format!("SUMMARY:{}", ics_escape(summary.as_ref())));
+        if let Some(ref name) = project.name {
+            lines.push(format!("CATEGORIES:{}", ics_escape(name.as_ref())));
+        }
+        if let Some(ref description) = subtask.description {
+            lines.push(format!("DESCRIPTION:{}", ics_escape(description.as_ref())));
+        }
+        if let Some(recorded) = stretch.timezone {
+            lines.push(format!("X-LOGTIME-TIMEZONE:{}", recorded.name()));
+        }
+        if let Some(ref branch) = subtask.branch {
+            lines.push(format!("X-LOGTIME-BRANCH:{}", ics_escape(branch.as_ref())));
+        }
+        lines.push(String::from("END:VEVENT"));
+    }
+    lines.push(String::from("END:VCALENDAR"));
+    for line in lines {
+        write!(output, "{}\r\n", ics_fold(line.as_ref()))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// Describes `tz` between two UTC times: the observance in effect at `from`,
+// then one for each change of offset, found by bisecting the day it falls in
+fn vtimezone(tz: Tz, from: NaiveDateTime, until: NaiveDateTime) -> Vec<String> {
+    let offset = |at: NaiveDateTime| tz.offset_from_utc_datetime(&at);
+    let same = |a: &<Tz as TimeZone>::Offset, b: &<Tz as TimeZone>::Offset| a.fix() == b.fix() && a.abbreviation() == b.abbreviation();
+    let mut lines = vec![String::from("BEGIN:VTIMEZONE"), format!("TZID:{}", tz.name())];
+    let first = offset(from);
+    lines.extend(observance(from, &first, &first));
+    let mut day = from;
+    while day < until {
+        let (before, after) = (offset(day), offset(day + Duration::days(1)));
+        if !same(&before, &after) {
+            let (mut low, mut high) = (day, day + Duration::days(1));
+            while high - low > Duration::seconds(1) {
+                let middle = low + (high - low) / 2;
+                if same(&offset(middle), &before) {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            lines.extend(observance(high, &before, &after));
+        }
+        day += Duration::days(1);
+    }
+    lines.push(String::from("END:VTIMEZONE"));
+    lines
+}
+
+// An observance starting at the UTC time `at`, whose DTSTART is the local
+// time in the offset it replaces
+fn observance(at: NaiveDateTime, before: &<Tz as TimeZone>::Offset, after: &<Tz as TimeZone>::Offset) -> Vec<String> {
+    let kind = if after.dst_offset().is_zero() { "STANDARD" } else { "DAYLIGHT" };
+    let local = at + Duration::seconds(before.fix().local_minus_utc() as i64);
+    vec![
+        format!("BEGIN:{}", kind),
+        format!("DTSTART:{}", local.format("%Y%m%dT%H%M%S")),
+        format!("TZOFFSETFROM:{}", ics_offset(before.fix())),
+        format!("TZOFFSETTO:{}", ics_offset(after.fix())),
+        format!("TZNAME:{}", ics_escape(after.abbreviation())),
+        format!("END:{}", kind),
+    ]
+}
+
+fn ics_offset(offset: FixedOffset) -> String {
+    let seconds = offset.local_minus_utc();
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    match seconds % 60 {
+        0 => format!("{}{:02}{:02}", sign, seconds / 3600, seconds % 3600 / 60),
+        rest => format!("{}{:02}{:02}{:02}", sign, seconds / 3600, seconds % 3600 / 60, rest),
+    }
+}
+
+fn ics_escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ';' => out.push_str("\\;"),
+            ',' => out.push_str("\\,"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {},
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// Content lines are limited to 75 octets; longer lines continue on the
+// next line after a single space.
+fn ics_fold(line: &str) -> String {
+    let mut out = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::naive::NaiveDate;
+    use super::vtimezone;
+
+    #[test]
+    fn observances() {
+        let from = NaiveDate::from_ymd(2026, 1, 1).and_hms(0, 0, 0);
+        let until = NaiveDate::from_ymd(2026, 12, 31).and_hms(0, 0, 0);
+        let lines = vtimezone(chrono_tz::Europe::Berlin, from, until);
+        assert_eq!(lines, vec![
+            "BEGIN:VTIMEZONE", "TZID:Europe/Berlin",
+            "BEGIN:STANDARD", "DTSTART:20260101T010000", "TZOFFSETFROM:+0100", "TZOFFSETTO:+0100", "TZNAME:CET", "END:STANDARD",
+            "BEGIN:DAYLIGHT", "DTSTART:20260329T020000", "TZOFFSETFROM:+0100", "TZOFFSETTO:+0200", "TZNAME:CEST", "END:DAYLIGHT",
+            "BEGIN:STANDARD", "DTSTART:20261025T030000", "TZOFFSETFROM:+0200", "TZOFFSETTO:+0100", "TZNAME:CET", "END:STANDARD",
+            "END:VTIMEZONE",
+        ]);
+        let lines = vtimezone(chrono_tz::Asia::Kolkata, from, until);
+        assert_eq!(lines[3..6], ["DTSTART:20260101T053000", "TZOFFSETFROM:+0530", "TZOFFSETTO:+0530"]);
+        assert_eq!(lines.len(), 9);
+    }
+}
@@ -0,0 +1,74 @@
+use std::fs::{File, OpenOptions};
+use std::io::Read;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+
+// The shell wrappers ask for a handshake file before running a command, and
+// pass it back to us with --fish, --zsh etc. We only write to files which we
+// could have created: regular files owned by us and private to us, opened
+// without following symlinks.
+
+pub fn create() -> Result<PathBuf, String> {
+    let dir = directory()?;
+    let mut random = [0u8; 16];
+    File::open("/dev/urandom")
+        .and_then(|mut urandom| urandom.read_exact(&mut random))
+        .map_err(|err| format!("Failed to read /dev/urandom: {}", err))?;
+    let name = random.iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    let path = dir.join(name);
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(&path)
+        .map_err(|err| format!("Failed to create {}: {}", path.display(), err))?;
+    Ok(path)
+}
+
+pub fn open(path: &str) -> Result<File, String> {
+    let file = OpenOptions::new()
+        .write(true)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK)
+        .open(path)
+        .map_err(|err| format!("Failed to open {}: {}", path, err))?;
+    let metadata = file.metadata()
+        .map_err(|err| format!("Failed to stat {}: {}", path, err))?;
+    if !metadata.is_file() {
+        return Err(format!("{} is not a regular file", path));
+    }
+    check_private(Path::new(path), &metadata)?;
+    file.set_len(0)
+        .map_err(|err| format!("Failed to truncate {}: {}", path, err))?;
+    Ok(file)
+}
+
+fn directory() -> Result<PathBuf, String> {
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime) if !runtime.is_empty() => PathBuf::from(runtime).join("logtime"),
+        _ => std::env::temp_dir().join(format!("logtime-{}", unsafe { libc::geteuid() })),
+    };
+    match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(ref err) if err.kind() == std::io::ErrorKind::AlreadyExists => {},
+        result => result.map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?,
+    }
+    let metadata = std::fs::symlink_metadata(&dir)
+        .map_err(|err| format!("Failed to stat {}: {}", dir.display(), err))?;
+    if !metadata.is_dir() {
+        return Err(format!("{} is not a directory", dir.display()));
+    }
+    check_private(&dir, &metadata)?;
+    Ok(dir)
+}
+
+fn check_private(path: &Path, metadata: &std::fs::Metadata) -> Result<(), String> {
+    if metadata.uid() != unsafe { libc::geteuid() } {
+        return Err(format!("{} is owned by another user", path.display()));
+    }
+    if metadata.mode() & 0o077 != 0 {
+        return Err(format!("{} is accessible to other users", path.display()));
+    }
+    Ok(())
+}
@@ -0,0 +1,173 @@
+pub mod timewarrior;
+pub mod watson;
+
+use std::io::{BufRead, BufReader, Read};
+
+use chrono::DateTime;
+use chrono_tz::Tz;
+use diesel::sqlite::SqliteConnection;
+use diesel::Connection;
+use crate::export::{Format, StretchRecord};
+use crate::models;
+
+pub struct Entry {
+    pub code: String,
+    pub start: DateTime<Tz>,
+    pub end: Option<DateTime<Tz>>,
+    pub branch: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Summary {
+    pub imported: usize,
+    pub new_projects: Vec<String>,
+    pub conflicts: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+pub struct Mapping {
+    rules: Vec<(String, String)>,
+}
+
+impl Mapping {
+    pub fn empty() -> Self {
+        Mapping { rules: Vec::new() }
+    }
+
+    pub fn load<R: Read>(input: R) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for (number, line) in BufReader::new(input).lines().enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.rsplitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(code), Some(key)) => {
+                    let code = code.trim();
+                    code.parse::<models::SubtaskSpec>()
+                        .map_err(|e| format!("line {}: {}: {}", number + 1, code, e))?;
+                    rules.push((key.trim().to_owned(), code.to_owned()));
+                },
+                _ => return Err(format!("line {}: expected <name> = <code>", number + 1)),
+            }
+        }
+        Ok(Mapping { rules: rules })
+    }
+
+    // Keys are tried in order; a key which is itself a valid code is used
+    // as-is when nothing maps it.
+    pub fn lookup<'a, I: IntoIterator<Item=&'a str>>(&self, keys: I) -> Option<String> {
+        let keys = keys.into_iter().collect::<Vec<_>>();
+        keys.iter()
+            .filter_map(|key| self.rules.iter()
+                        .find(|(k, _)| k == key)
+                        .map(|(_, code)| code.clone()))
+            .next()
+            .or_else(|| keys.iter()
+                     .find(|key| key.parse::<models::SubtaskSpec>().is_ok())
+                     .map(|key| String::from(*key)))
+    }
+}
+
+impl Entry {
+    pub fn span(&self) -> String {
+        match self.end {
+            None => format!("{} {} - ", self.code, self.start.format(models::TIME_FORMAT)),
+            Some(end) => format!("{} {} - {}", self.code, self.start.format(models::TIME_FORMAT), end.format(models::TIME_FORMAT)),
+        }
+    }
+}
+
+impl std::convert::TryFrom<StretchRecord> for Entry {
+    type Error = String;
+    fn try_from(record: StretchRecord) -> Result<Self, Self::Error> {
+        let tz = match record.timezone.as_ref().filter(|tz| !tz.is_empty()) {
+            Some(tz) => tz.parse::<Tz>().map_err(|_| format!("Unknown timezone: {}", tz))?,
+            None => models::current_timezone(),
+        };
+        Ok(Entry {
+            code: format!("{}-{}-{}", record.project, record.task, record.subtask),
+            start: models::parse_time(record.start.as_ref())?.with_timezone(&tz),
+            end: record.end.as_ref()
+                .filter(|end| !end.is_empty())
+                .map(|end| models::parse_time(end.as_ref()).map(|end| end.with_timezone(&tz)))
+                .transpose()?,
+            branch: record.branch.filter(|branch| !branch.is_empty()),
+            description: record.description.filter(|description| !description.is_empty()),
+        })
+    }
+}
+
+pub fn format_for_path(path: &str) -> Option<Format> {
+    std::path::Path::new(path).extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(|extension| extension.parse().ok())
+}
+
+pub fn read_records<R: Read>(input: R, format: Format) -> Result<Vec<Entry>, String> {
+    use std::convert::TryFrom;
+    let records = match format {
+        Format::Csv => csv::Reader::from_reader(input)
+            .deserialize::<StretchRecord>()
+            .collect::<Result<Vec<_>,_>>()
+            .map_err(|e| e.to_string())?,
+        Format::Json => serde_json::from_reader::<_, Vec<StretchRecord>>(input)
+            .map_err(|e| e.to_string())?,
+        Format::Jsonl => BufReader::new(input).lines()
+            .filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+            .map(|line| line.map_err(|e| e.to_string())
+                 .and_then(|line| serde_json::from_str::<StretchRecord>(line.as_ref())
+                           .map_err(|e| e.to_string())))
+            .collect::<Result<Vec<_>,_>>()?,
+        Format::Ics => return Err(String::from("Importing ics files is not supported")),
+    };
+    records.into_iter()
+        .map(Entry::try_from)
+        .collect()
+}
+
+pub fn import(conn: &SqliteConnection, entries: Vec<Entry>, dry_run: bool) -> Result<Summary, models::DbOrMiscError> {
+    let mut summary = Summary::default();
+    let result = SqliteConnection::transaction(conn, || {
+        for entry in entries.iter() {
+            let end = match entry.end {
+                Some(end) => end,
+                None => {
+                    summary.skipped.push(format!("{}: stretch has no end", entry.span()));
+                    continue;
+                },
+            };
+            if let Some(other) = models::Stretch::overlapping(conn, entry.start, end, None)?.first() {
+                summary.conflicts.push(format!("{}: overlaps stretch {} ({})", entry.span(), other.id, other.span()));
+                continue;
+            }
+            let spec: models::SubtaskSpec = entry.code.parse()?;
+            match models::Project::by_code(conn, spec.project_code.as_ref()) {
+                Err(diesel::result::Error::NotFound) => { summary.new_projects.push(spec.project_code.clone()); },
+                result => { result?; },
+            }
+            let (_project,_task,mut subtask) = models::Subtask
+                ::for_code(conn, entry.code.as_ref())?;
+            if subtask.branch.is_none() && entry.branch.is_some() {
+                subtask.set_branch(conn, entry.branch.clone())?;
+            }
+            if subtask.description.is_none() && entry.description.is_some() {
+                subtask.set_description(conn, entry.description.clone())?;
+            }
+            subtask.add_stretch(conn, entry.start, end)?;
+            summary.imported += 1;
+        }
+        if dry_run {
+            Err(models::DbOrMiscError::from(diesel::result::Error::RollbackTransaction))
+        } else {
+            Ok(())
+        }
+    });
+    match result {
+        Err(models::DbOrMiscError::Db(diesel::result::Error::RollbackTransaction)) if dry_run => Ok(summary),
+        result => result.map(|()| summary),
+    }
+}
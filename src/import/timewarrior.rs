@@ -0,0 +1,234 @@
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use chrono::DateTime;
+use chrono::naive::NaiveDateTime;
+use chrono::offset::{TimeZone, Utc};
+use chrono_tz::Tz;
+use super::{Entry, Mapping};
+use crate::models;
+
+pub fn read_path(path: &Path, mapping: &Mapping) -> Result<(Vec<Entry>, Vec<String>), String> {
+    let mut files = if path.is_dir() {
+        std::fs::read_dir(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file| is_month_file(file))
+            .collect::<Vec<_>>()
+    } else {
+        vec![path.to_path_buf()]
+    };
+    files.sort();
+    let mut entries = Vec::new();
+    let mut skipped = Vec::new();
+    for file in files {
+        let input = std::fs::File::open(&file)
+            .map_err(|e| format!("Failed to open {}: {}", file.display(), e))?;
+        let (mut e, mut s) = read(input, mapping)
+            .map_err(|e| format!("{}: {}", file.display(), e))?;
+        entries.append(&mut e);
+        skipped.append(&mut s);
+    }
+    Ok((entries, skipped))
+}
+
+// Intervals are kept in one file per month, such as 2026-10.data; the same
+// directory also holds tags.data and undo.data, which are not intervals
+fn is_month_file(file: &Path) -> bool {
+    let name = match file.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name,
+        None => return false,
+    };
+    let bytes = name.as_bytes();
+    name.len() == "YYYY-MM.data".len()
+        && name.ends_with(".data")
+        && bytes[4] == b'-'
+        && bytes[..4].iter().chain(bytes[5..7].iter()).all(|b| b.is_ascii_digit())
+}
+
+pub fn read<R: Read>(input: R, mapping: &Mapping) -> Result<(Vec<Entry>, Vec<String>), String> {
+    let mut entries = Vec::new();
+    let mut skipped = Vec::new();
+    for (number, line) in BufReader::new(input).lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let interval = parse_line(line.as_ref())
+            .map_err(|e| format!("line {}: {}", number + 1, e))?;
+        match mapping.lookup(interval.tags.iter().map(|tag| tag.as_ref())) {
+            Some(code) => entries.push(Entry {
+                code: code,
+                start: interval.start,
+                end: interval.end,
+                branch: None,
+                description: interval.annotation,
+            }),
+            None => skipped.push(format!("{}: no mapping for tags {:?}", line.trim(), interval.tags)),
+        }
+    }
+    Ok((entries, skipped))
+}
+
+struct Interval {
+    start: DateTime<Tz>,
+    end: Option<DateTime<Tz>>,
+    tags: Vec<String>,
+    annotation: Option<String>,
+}
+
+// inc <start> [- <end>] [# <tag> ... [# <annotation>]]
+fn parse_line(line: &str) -> Result<Interval, String> {
+    let tokens = tokenize(line)?;
+    let mut tokens = tokens.into_iter();
+    match tokens.next() {
+        Some((ref word, false)) if word == "inc" => {},
+        _ => return Err(String::from("expected inc")),
+    }
+    let start = tokens.next()
+        .ok_or_else(|| String::from("missing start time"))
+        .and_then(|(time, _)| parse_time(time.as_ref()))?;
+    let mut end = None;
+    let mut tags = Vec::new();
+    let mut annotation = Vec::new();
+    let mut section = 0;
+    while let Some((token, quoted)) = tokens.next() {
+        match (section, token.as_ref(), quoted) {
+            (0, "-", false) => {
+                end = Some(tokens.next()
+                    .ok_or_else(|| String::from("missing end time"))
+                    .and_then(|(time, _)| parse_time(time.as_ref()))?);
+            },
+            (0, "#", false) | (1, "#", false) => { section += 1; },
+            (1, _, _) => tags.push(token),
+            (2, _, _) => annotation.push(token),
+            _ => return Err(format!("unexpected {:?}", token)),
+        }
+    }
+    Ok(Interval {
+        start: start,
+        end: end,
+        tags: tags,
+        annotation: if annotation.is_empty() { None } else { Some(annotation.join(" ")) },
+    })
+}
+
+fn tokenize(line: &str) -> Result<Vec<(String, bool)>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c == '"' {
+            let mut token = String::new();
+            loop {
+                match chars.next() {
+                    None => return Err(String::from("unterminated quote")),
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(escaped) => token.push(escaped),
+                        None => return Err(String::from("unterminated quote")),
+                    },
+                    Some(other) => token.push(other),
+                }
+            }
+            tokens.push((token, true));
+        } else {
+            let mut token = c.to_string();
+            while let Some(&next) = chars.peek() {
+                if next.is_whitespace() {
+                    break;
+                }
+                token.push(next);
+                chars.next();
+            }
+            tokens.push((token, false));
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_time(time: &str) -> Result<DateTime<Tz>, String> {
+    NaiveDateTime::parse_from_str(time, "%Y%m%dT%H%M%SZ")
+        .map(|naive| Utc.from_utc_datetime(&naive).with_timezone(&models::current_timezone()))
+        .map_err(|e| format!("{}: {}", time, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timestamp(time: &str) -> i64 {
+        Utc.datetime_from_str(time, "%Y-%m-%d %H:%M:%S").unwrap().timestamp()
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(tokenize(r#"inc  a "b c" "d\"e" """#).unwrap(), vec![
+            (String::from("inc"), false),
+            (String::from("a"), false),
+            (String::from("b c"), true),
+            (String::from("d\"e"), true),
+            (String::from(""), true),
+        ]);
+        assert!(tokenize(r#"inc "open"#).is_err());
+        assert!(tokenize(r#"inc "open\"#).is_err());
+    }
+
+    #[test]
+    fn closed_interval_with_quoted_tags_and_annotation() {
+        let interval = parse_line(r#"inc 20261017T080000Z - 20261017T093000Z # ABC-1-2 "two words" # "fixed the \"bug\"""#).unwrap();
+        assert_eq!(interval.start.timestamp(), timestamp("2026-10-17 08:00:00"));
+        assert_eq!(interval.end.map(|end| end.timestamp()), Some(timestamp("2026-10-17 09:30:00")));
+        assert_eq!(interval.tags, vec!["ABC-1-2", "two words"]);
+        assert_eq!(interval.annotation.as_ref().map(|a| a.as_ref()), Some("fixed the \"bug\""));
+    }
+
+    #[test]
+    fn open_interval() {
+        let interval = parse_line("inc 20261017T080000Z # ABC-1-2").unwrap();
+        assert_eq!(interval.start.timestamp(), timestamp("2026-10-17 08:00:00"));
+        assert!(interval.end.is_none());
+        assert_eq!(interval.tags, vec!["ABC-1-2"]);
+        assert!(interval.annotation.is_none());
+        let interval = parse_line("inc 20261017T080000Z").unwrap();
+        assert!(interval.end.is_none() && interval.tags.is_empty());
+    }
+
+    #[test]
+    fn invalid_lines() {
+        for line in ["exc 20261017T080000Z", "inc", "inc 2026-10-17", "inc 20261017T080000Z -",
+                     "inc 20261017T080000Z tag", "inc 20261017T080000Z - # tag"] {
+            assert!(parse_line(line).is_err(), "{:?} parsed", line);
+        }
+    }
+
+    #[test]
+    fn read_maps_tags_and_skips_the_rest() {
+        let mapping = Mapping::load("work = ABC-1-2\n".as_bytes()).unwrap();
+        let input = "inc 20261017T080000Z - 20261017T090000Z # work\n\ninc 20261017T100000Z - 20261017T110000Z # XYZ-3-1 # note\ninc 20261017T120000Z - 20261017T130000Z # lunch\n";
+        let (entries, skipped) = read(input.as_bytes(), &mapping).unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.code.as_ref()).collect::<Vec<&str>>(), vec!["ABC-1-2", "XYZ-3-1"]);
+        assert_eq!(entries[1].description.as_ref().map(|d| d.as_ref()), Some("note"));
+        assert_eq!(skipped.len(), 1);
+        match read("inc 20261017T080000Z\n{}\n".as_bytes(), &mapping) {
+            Err(e) => assert!(e.starts_with("line 2:"), "{}", e),
+            Ok(_) => panic!("tags.data style line parsed"),
+        }
+    }
+
+    #[test]
+    fn directory_reads_only_month_files() {
+        let dir = std::env::temp_dir().join(format!("logtime-timewarrior-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("2026-10.data"), "inc 20261017T080000Z - 20261017T090000Z # ABC-1-2\n").unwrap();
+        std::fs::write(dir.join("2026-09.data"), "inc 20260917T080000Z - 20260917T090000Z # ABC-1-1\n").unwrap();
+        std::fs::write(dir.join("tags.data"), "{\"ABC-1-2\":{\"count\":1}}\n").unwrap();
+        std::fs::write(dir.join("undo.data"), "txn:\n  type: interval\n").unwrap();
+        let (entries, skipped) = read_path(&dir, &Mapping::empty()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.code.as_ref()).collect::<Vec<&str>>(), vec!["ABC-1-1", "ABC-1-2"]);
+        assert!(skipped.is_empty());
+    }
+}
@@ -0,0 +1,57 @@
+use std::io::Read;
+
+use chrono::offset::TimeZone;
+use serde::Deserialize;
+use super::{Entry, Mapping};
+use crate::models;
+
+// Watson stores each frame as [start, stop, project, id, tags, updated_at]
+#[derive(Deserialize)]
+struct Frame(i64, i64, String, String, #[serde(default)] Vec<String>, #[serde(default)] serde::de::IgnoredAny);
+
+pub fn read<R: Read>(input: R, mapping: &Mapping) -> Result<(Vec<Entry>, Vec<String>), String> {
+    let frames = serde_json::from_reader::<_, Vec<Frame>>(input)
+        .map_err(|e| e.to_string())?;
+    let tz = models::current_timezone();
+    let mut entries = Vec::new();
+    let mut skipped = Vec::new();
+    for Frame(start, stop, project, id, tags, _) in frames {
+        let keys = tags.iter()
+            .map(|tag| format!("{}:{}", project, tag))
+            .chain(std::iter::once(project.clone()))
+            .collect::<Vec<_>>();
+        match mapping.lookup(keys.iter().map(|key| key.as_ref())) {
+            Some(code) => entries.push(Entry {
+                code: code,
+                start: tz.timestamp(start, 0),
+                end: Some(tz.timestamp(stop, 0)),
+                branch: None,
+                description: None,
+            }),
+            None => skipped.push(format!("frame {}: no mapping for project {:?} with tags {:?}", id, project, tags)),
+        }
+    }
+    Ok((entries, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames() {
+        let mapping = Mapping::load("client:bugfix = ABC-1-2\nclient = ABC-1-1\n".as_bytes()).unwrap();
+        let input = r#"[
+            [1792224000, 1792227600, "client", "a1", ["bugfix"], 1792227600],
+            [1792231200, 1792234800, "client", "b2", []],
+            [1792238400, 1792242000, "XYZ-3-1", "c3", ["other"], 1792242000],
+            [1792245600, 1792249200, "unknown", "d4", ["x"], 1792249200]
+        ]"#;
+        let (entries, skipped) = read(input.as_bytes(), &mapping).unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.code.as_ref()).collect::<Vec<&str>>(), vec!["ABC-1-2", "ABC-1-1", "XYZ-3-1"]);
+        assert_eq!(entries[0].start.timestamp(), 1792224000);
+        assert_eq!(entries[0].end.map(|end| end.timestamp()), Some(1792227600));
+        assert_eq!(skipped, vec![String::from("frame d4: no mapping for project \"unknown\" with tags [\"x\"]")]);
+        assert!(read("[[1, 2]]".as_bytes(), &mapping).is_err());
+    }
+}
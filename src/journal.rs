@@ -0,0 +1,292 @@
+use std::time::SystemTime;
+
+use diesel::sqlite::SqliteConnection;
+use diesel::{Connection, QueryDsl, RunQueryDsl, ExpressionMethods};
+use crate::models::DbOrMiscError;
+use crate::schema;
+
+// Operations are recorded while in the RECORDING state: the triggers added
+// alongside the journal table only write entries when such an operation
+// exists, so undo and redo can replay entries without journaling themselves.
+const RECORDING: i32 = 0;
+const DONE: i32 = 1;
+const UNDONE: i32 = 2;
+
+const TABLES: &[(&str, &[&str])] = &[
+    ("projects", &["id", "code", "directory", "name"]),
+    ("tasks", &["id", "project_id", "number", "active_subtask"]),
+    ("subtasks", &["id", "task_id", "branch", "description", "active", "number"]),
+    ("stretches", &["id", "subtask_id", "start", "end", "timezone"]),
+    ("pauses", &["id", "subtask_id", "paused"]),
+];
+
+#[derive(Queryable)]
+pub struct Operation {
+    pub id: i64,
+    pub command: String,
+}
+
+#[derive(Queryable)]
+struct Entry {
+    id: i64,
+    table_name: String,
+    action: String,
+    row_id: i64,
+}
+
+pub fn begin(conn: &SqliteConnection, command: &str) -> Result<i64, diesel::result::Error> {
+    use schema::operations;
+    use schema::operations::dsl;
+    #[derive(Insertable)]
+    #[table_name="operations"]
+    struct NewOperation<'x> {
+        command: &'x str,
+        created: i64,
+        state: i32,
+        pid: i64,
+    }
+    SqliteConnection::transaction(conn, || {
+        close_stale(conn)?;
+        diesel::insert_into(operations::table)
+            .values(&NewOperation {
+                command: command,
+                created: SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64,
+                state: RECORDING,
+                pid: std::process::id() as i64,
+            })
+            .execute(conn)?;
+        dsl::operations.select(diesel::dsl::max(dsl::id))
+            .get_result::<Option<i64>>(conn)?
+            .ok_or(diesel::result::Error::NotFound)
+    })
+}
+
+pub fn finish(conn: &SqliteConnection, operation: i64) -> Result<(), diesel::result::Error> {
+    use schema::operations::dsl;
+    SqliteConnection::transaction(conn, || {
+        let entries = schema::journal::dsl::journal
+            .filter(schema::journal::dsl::operation_id.eq(operation))
+            .count()
+            .get_result::<i64>(conn)?;
+        if entries == 0 {
+            diesel::delete(dsl::operations.filter(dsl::id.eq(operation)))
+                .execute(conn)?;
+        } else {
+            // A new change invalidates anything that could have been redone
+            let undone = dsl::operations.filter(dsl::state.eq(UNDONE))
+                .select(dsl::id)
+                .load::<i64>(conn)?;
+            delete_operations(conn, undone)?;
+            diesel::update(dsl::operations.filter(dsl::id.eq(operation)))
+                .set(dsl::state.eq(DONE))
+                .execute(conn)?;
+        }
+        Ok(())
+    })
+}
+
+pub fn undo(conn: &SqliteConnection) -> Result<Option<Operation>, DbOrMiscError> {
+    use schema::operations::dsl;
+    SqliteConnection::transaction(conn, || {
+        close_stale(conn)?;
+        let operation = match dsl::operations.filter(dsl::state.eq(DONE))
+            .order(dsl::id.desc())
+            .select((dsl::id, dsl::command))
+            .first::<Operation>(conn) {
+                Err(diesel::result::Error::NotFound) => return Ok(None),
+                result => result?,
+            };
+        for entry in entries(conn, operation.id)?.iter().rev() {
+            match entry.action.as_ref() {
+                "insert" => delete_row(conn, entry)?,
+                "update" => update_row(conn, entry, "old_values")?,
+                "delete" => insert_row(conn, entry, "old_values")?,
+                other => return Err(DbOrMiscError::from(format!("Unknown journal action {:?}", other))),
+            }
+        }
+        diesel::update(dsl::operations.filter(dsl::id.eq(operation.id)))
+            .set(dsl::state.eq(UNDONE))
+            .execute(conn)?;
+        Ok(Some(operation))
+    })
+}
+
+pub fn redo(conn: &SqliteConnection) -> Result<Option<Operation>, DbOrMiscError> {
+    use schema::operations::dsl;
+    SqliteConnection::transaction(conn, || {
+        close_stale(conn)?;
+        let operation = match dsl::operations.filter(dsl::state.eq(UNDONE))
+            .order(dsl::id.asc())
+            .select((dsl::id, dsl::command))
+            .first::<Operation>(conn) {
+                Err(diesel::result::Error::NotFound) => return Ok(None),
+                result => result?,
+            };
+        for entry in entries(conn, operation.id)?.iter() {
+            match entry.action.as_ref() {
+                "insert" => insert_row(conn, entry, "new_values")?,
+                "update" => update_row(conn, entry, "new_values")?,
+                "delete" => delete_row(conn, entry)?,
+                other => return Err(DbOrMiscError::from(format!("Unknown journal action {:?}", other))),
+            }
+        }
+        diesel::update(dsl::operations.filter(dsl::id.eq(operation.id)))
+            .set(dsl::state.eq(DONE))
+            .execute(conn)?;
+        Ok(Some(operation))
+    })
+}
+
+// An operation left recording (e.g. if logtime was killed) is kept as done
+// so that it can still be undone. One whose process is still running
+// belongs to a concurrent command and is left alone.
+fn close_stale(conn: &SqliteConnection) -> Result<(), diesel::result::Error> {
+    use schema::operations::dsl;
+    let stale = dsl::operations.filter(dsl::state.eq(RECORDING))
+        .select((dsl::id, dsl::pid))
+        .load::<(i64, Option<i64>)>(conn)?
+        .into_iter()
+        .filter(|(_, pid)| pid.map_or(true, |pid| pid == std::process::id() as i64 || !running(pid)))
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    diesel::update(dsl::operations.filter(dsl::id.eq_any(&stale)))
+        .set(dsl::state.eq(DONE))
+        .execute(conn)
+        .map(|_| ())
+}
+
+fn running(pid: i64) -> bool {
+    let alive = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0;
+    alive || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+fn delete_operations(conn: &SqliteConnection, ids: Vec<i64>) -> Result<(), diesel::result::Error> {
+    use schema::operations::dsl;
+    diesel::delete(schema::journal::dsl::journal
+                   .filter(schema::journal::dsl::operation_id.eq_any(&ids)))
+        .execute(conn)?;
+    diesel::delete(dsl::operations.filter(dsl::id.eq_any(&ids)))
+        .execute(conn)
+        .map(|_| ())
+}
+
+fn entries(conn: &SqliteConnection, operation: i64) -> Result<Vec<Entry>, diesel::result::Error> {
+    use schema::journal::dsl;
+    dsl::journal.filter(dsl::operation_id.eq(operation))
+        .order(dsl::id.asc())
+        .select((dsl::id, dsl::table_name, dsl::action, dsl::row_id))
+        .load::<Entry>(conn)
+}
+
+fn columns(table: &str) -> Result<&'static [&'static str], DbOrMiscError> {
+    TABLES.iter()
+        .find(|(name, _)| *name == table)
+        .map(|(_, columns)| *columns)
+        .ok_or_else(|| DbOrMiscError::from(format!("Unknown journal table {:?}", table)))
+}
+
+fn quote(column: &str) -> String {
+    format!("\"{}\"", column)
+}
+
+fn delete_row(conn: &SqliteConnection, entry: &Entry) -> Result<(), DbOrMiscError> {
+    columns(entry.table_name.as_ref())?;
+    diesel::sql_query(format!("DELETE FROM {} WHERE id = {}", entry.table_name, entry.row_id))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(DbOrMiscError::from)
+}
+
+fn insert_row(conn: &SqliteConnection, entry: &Entry, values: &str) -> Result<(), DbOrMiscError> {
+    let columns = columns(entry.table_name.as_ref())?;
+    diesel::sql_query(format!(
+            "INSERT INTO {} ({}) SELECT {} FROM journal WHERE id = {}",
+            entry.table_name,
+            columns.iter().map(|c| quote(c)).collect::<Vec<_>>().join(", "),
+            columns.iter().map(|c| format!("json_extract({}, '$.{}')", values, c)).collect::<Vec<_>>().join(", "),
+            entry.id))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(DbOrMiscError::from)
+}
+
+fn update_row(conn: &SqliteConnection, entry: &Entry, values: &str) -> Result<(), DbOrMiscError> {
+    let columns = columns(entry.table_name.as_ref())?;
+    diesel::sql_query(format!(
+            "UPDATE {} SET {} WHERE id = {}",
+            entry.table_name,
+            columns.iter()
+                .map(|c| format!("{} = (SELECT json_extract({}, '$.{}') FROM journal WHERE id = {})", quote(c), values, c, entry.id))
+                .collect::<Vec<_>>()
+                .join(", "),
+            entry.row_id))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(DbOrMiscError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::offset::TimeZone;
+    use diesel::sqlite::SqliteConnection;
+    use diesel::RunQueryDsl;
+    use crate::{logtimedb, models};
+
+    // Every row of every journaled table, so that states can be compared
+    fn snapshot(conn: &SqliteConnection) -> Vec<String> {
+        super::TABLES.iter()
+            .map(|(table, columns)| diesel::select(diesel::dsl::sql::<diesel::sql_types::Text>(format!(
+                    "(SELECT coalesce(json_group_array(json_array({})), '[]') FROM (SELECT * FROM {} ORDER BY id))",
+                    columns.iter().map(|c| super::quote(c)).collect::<Vec<_>>().join(", "),
+                    table).as_ref()))
+                .get_result::<String>(conn)
+                .unwrap())
+            .collect()
+    }
+
+    fn start(conn: &SqliteConnection, code: &str, hour: u32) {
+        let at = chrono_tz::UTC.ymd(2026, 10, 18).and_hms(hour, 0, 0);
+        let (_, _, subtask) = models::Subtask::for_code(conn, code).unwrap();
+        models::Stretch::stop_all_at(conn, Some(at)).unwrap();
+        subtask.begin_at(conn, at).unwrap();
+    }
+
+    #[test]
+    fn undo_and_redo_start_of_a_new_subtask() {
+        let conn = logtimedb::open(":memory:").unwrap();
+        start(&conn, "ab-1-1", 8);
+        let before = snapshot(&conn);
+
+        let operation = super::begin(&conn, "start xy-2-1").unwrap();
+        start(&conn, "xy-2-1", 9);
+        super::finish(&conn, operation).unwrap();
+        let after = snapshot(&conn);
+        assert_ne!(before, after);
+
+        assert_eq!(super::undo(&conn).unwrap().map(|operation| operation.command), Some(String::from("start xy-2-1")));
+        assert_eq!(snapshot(&conn), before);
+        assert!(super::undo(&conn).unwrap().is_none());
+
+        assert_eq!(super::redo(&conn).unwrap().map(|operation| operation.id), Some(operation));
+        assert_eq!(snapshot(&conn), after);
+        assert!(super::redo(&conn).unwrap().is_none());
+
+        super::undo(&conn).unwrap();
+        assert_eq!(snapshot(&conn), before);
+    }
+
+    #[test]
+    fn concurrent_operations_are_not_closed() {
+        let conn = logtimedb::open(":memory:").unwrap();
+        diesel::sql_query("INSERT INTO operations(command, created, state, pid) VALUES ('live', 0, 0, 1), ('dead', 0, 0, NULL)")
+            .execute(&conn)
+            .unwrap();
+        let operation = super::begin(&conn, "start ab-1-1").unwrap();
+        let states = diesel::select(diesel::dsl::sql::<diesel::sql_types::Text>(
+                "(SELECT group_concat(command || '=' || state, ' ') FROM operations ORDER BY id)"))
+            .get_result::<String>(&conn)
+            .unwrap();
+        assert_eq!(states, "live=0 dead=1 start ab-1-1=0");
+        super::finish(&conn, operation).unwrap();
+    }
+}
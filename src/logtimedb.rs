@@ -1,20 +1,81 @@
+use diesel::RunQueryDsl;
 use diesel::connection::Connection;
 use diesel::sqlite::SqliteConnection;
 use diesel_migrations::embed_migrations;
 
 embed_migrations!();
 
-pub fn open_default() -> Result<SqliteConnection,String> {
+pub fn default_path() -> Result<String,String> {
     let home = std::env::var("HOME")
         .map_err(|err| format!("Couldn't find home directory: {}", err))?;
     let mut storage = std::path::PathBuf::from(home);
     storage.push(".logtime.sqlite");
-    open(storage.as_path().to_string_lossy())
+    Ok(storage.as_path().to_string_lossy().into_owned())
 }
 
 pub fn open<P: AsRef<str>>(path: P) -> Result<SqliteConnection,String> {
-    let connection = SqliteConnection::establish(path.as_ref())
-        .map_err(|err| format!("Failed to open {:?}: {}", path.as_ref(), err))?;
+    let connection = open_without_migrations(path)?;
     embedded_migrations::run(&connection).map_err(|err| format!("Failed to run migrations: {}", err))?;
     Ok(connection)
 }
+
+// For read-only callers which must be fast, such as prompts
+pub fn open_without_migrations<P: AsRef<str>>(path: P) -> Result<SqliteConnection,String> {
+    SqliteConnection::establish(path.as_ref())
+        .map_err(|err| format!("Failed to open {:?}: {}", path.as_ref(), err))
+}
+
+// The version of the latest migration in migrations/
+const SCHEMA_VERSION: &str = "20261018040000";
+
+// Checks that every migration has been applied to a database opened without
+// running them, so that its queries can be trusted
+pub fn check_schema(conn: &SqliteConnection) -> Result<(), String> {
+    let version = diesel::select(diesel::dsl::sql::<diesel::sql_types::Nullable<diesel::sql_types::Text>>(
+            "(SELECT max(version) FROM __diesel_schema_migrations)"))
+        .get_result::<Option<String>>(conn)
+        .ok()
+        .flatten();
+    match version {
+        Some(ref version) if version == SCHEMA_VERSION => Ok(()),
+        _ => Err(String::from("The database is not up to date; run any other logtime command to migrate it")),
+    }
+}
+
+// Cached summary of the current task, kept next to the database
+pub fn state_path<P: AsRef<str>>(path: P) -> std::path::PathBuf {
+    std::path::PathBuf::from(format!("{}.state", path.as_ref()))
+}
+
+// Last shell activity, recorded by `logtime touch`
+pub fn activity_path<P: AsRef<str>>(path: P) -> std::path::PathBuf {
+    std::path::PathBuf::from(format!("{}.activity", path.as_ref()))
+}
+
+// Replaces a file next to the database through a temporary file named after
+// this process, so that concurrent writers never rename each other's
+// partial files
+pub fn write_file(path: &std::path::Path, contents: &[u8]) -> Result<(), String> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(format!(".{}.new", std::process::id()));
+    std::fs::write(&partial, contents)
+        .and_then(|_| std::fs::rename(&partial, path))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&partial);
+            format!("Failed to write {}: {}", path.display(), e)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn schema_version_is_latest_migration() {
+        let latest = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter_map(|name| name.split('_').next().map(|version| version.replace('-', "")))
+            .max()
+            .unwrap();
+        assert_eq!(super::SCHEMA_VERSION, latest);
+    }
+}
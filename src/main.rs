@@ -3,70 +3,184 @@
 mod schema;
 mod models;
 mod logtimedb;
+mod journal;
+mod export;
+mod import;
 mod shell;
 mod commands;
+mod cli;
+mod completions;
+mod handshake;
+mod status;
+mod config;
+mod timeexpr;
+mod activity;
 
-use std::fs::File;
 use shell::fish::Fish;
 use shell::zsh::Zsh;
+use shell::bash::Bash;
+use shell::sh::Sh;
+use shell::nushell::Nushell;
+
+// Commands which must not be recorded in the undo journal: undo and redo
+// manage it themselves, and the rest only read, so they should not need
+// to write to the database at all
+const JOURNAL_EXEMPT: &[&str] = &["undo", "redo", "current", "status", "cd", "display", "log", "recent", "export", "completions"];
 
 fn main() {
-    let mut args = std::env::args();
-    let mut dbspec = None;
-    let mut shell_out = Vec::new();
-    let mut cmd = None;
-    args.next();
-    while let Some(arg) = args.next() {
-        match arg.as_ref() {
-            "--fish" => {
-                let path = args.next()
-                    .expect("--fish requires output path");
-                match File::create(path.clone()) {
-                    Ok(file) => {shell_out.push(Box::new(Fish::new(file)) as Box<dyn shell::Shell>);},
-                    Err(err) => {eprintln!("failed to open {}: {:?}", path, err);},
-                }
-            },
-            "--zsh" => {
-                let path = args.next()
-                    .expect("--zsh requires output path");
-                match File::create(path.clone()) {
-                    Ok(file) => {shell_out.push(Box::new(Zsh::new(file)) as Box<dyn shell::Shell>);},
-                    Err(err) => {eprintln!("failed to open {}: {:?}", path, err);},
-                }
-            },
-            "--db" => {
-                let path = args.next()
-                    .expect("--db requires sqlite database file location");
-                dbspec = Some(path);
+    let matches = cli::app().get_matches();
+    if let Some(tz) = matches.get_one::<String>("tz") {
+        std::env::set_var("LOGTIME_TZ", tz);
+    }
+    if let Some(("completions", args)) = matches.subcommand() {
+        let stdout = std::io::stdout();
+        completions::generate(args.get_one::<String>("shell").unwrap(), cli::public_app(), &mut stdout.lock())
+            .unwrap_or_else(|err| eprintln!("{}", err));
+        return;
+    }
+    if let Some(("__handshake", _)) = matches.subcommand() {
+        match handshake::create() {
+            Ok(path) => println!("{}", path.display()),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
             },
-            arg => {
-                cmd = Some(arg.to_owned());
-                break;
+        }
+        return;
+    }
+    let db_path = match matches.get_one::<String>("db") {
+        Some(path) => path.clone(),
+        None => logtimedb::default_path().unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }),
+    };
+    if let Some(("prompt", args)) = matches.subcommand() {
+        if let Err(err) = commands::prompt(args, db_path.as_ref()) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(("__complete", args)) = matches.subcommand() {
+        if let Err(err) = commands::complete(args, db_path.as_ref()) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(("touch", _)) = matches.subcommand() {
+        match activity::touch(db_path.as_ref(), models::now().timestamp()) {
+            Ok(Some(idle)) => eprintln!("{}; see `logtime idle`", idle.describe()),
+            Ok(None) => {},
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
             },
         }
+        return;
     }
-    match cmd {
-        Some(cmd) => {
-            let database = match dbspec {
-                None => logtimedb::open_default().unwrap(),
-                Some(path) => logtimedb::open(path).unwrap(),
-            };
-            run_cmd(cmd.as_ref(), &mut args, &database, &mut shell_out);
-        },
-        None => {
-            eprintln!("Usage: logtime [--fish <filename>] [--db <filename>] <command> <command args>");
+    let mut shell_out = Vec::new();
+    for path in matches.get_many::<String>("fish").into_iter().flatten() {
+        match handshake::open(path) {
+            Ok(file) => {shell_out.push(Box::new(Fish::new(file)) as Box<dyn shell::Shell>);},
+            Err(err) => {eprintln!("{}", err);},
+        }
+    }
+    for path in matches.get_many::<String>("zsh").into_iter().flatten() {
+        match handshake::open(path) {
+            Ok(file) => {shell_out.push(Box::new(Zsh::new(file)) as Box<dyn shell::Shell>);},
+            Err(err) => {eprintln!("{}", err);},
+        }
+    }
+    for path in matches.get_many::<String>("bash").into_iter().flatten() {
+        match handshake::open(path) {
+            Ok(file) => {shell_out.push(Box::new(Bash::new(file)) as Box<dyn shell::Shell>);},
+            Err(err) => {eprintln!("{}", err);},
+        }
+    }
+    for path in matches.get_many::<String>("sh").into_iter().flatten() {
+        match handshake::open(path) {
+            Ok(file) => {shell_out.push(Box::new(Sh::new(file)) as Box<dyn shell::Shell>);},
+            Err(err) => {eprintln!("{}", err);},
         }
     }
+    for path in matches.get_many::<String>("nu").into_iter().flatten() {
+        match handshake::open(path) {
+            Ok(file) => {shell_out.push(Box::new(Nushell::new(file)) as Box<dyn shell::Shell>);},
+            Err(err) => {eprintln!("{}", err);},
+        }
+    }
+    let database = logtimedb::open(&db_path).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    let (cmd, args) = matches.subcommand().unwrap();
+    match activity::touch(db_path.as_ref(), models::now().timestamp()) {
+        Ok(Some(idle)) if cmd != "idle" && cmd != "fix-open" => eprintln!("{}; see `logtime idle`", idle.describe()),
+        Ok(_) => {},
+        Err(err) => eprintln!("Failed to record activity: {}", err),
+    }
+    let operation = if JOURNAL_EXEMPT.contains(&cmd) {
+        None
+    } else {
+        journal::begin(&database, command_line().as_ref())
+            .map_err(|err| eprintln!("Failed to start journal entry: {}", err))
+            .ok()
+    };
+    let result = run_cmd(cmd, args, &database, db_path.as_ref(), &mut shell_out);
+    if let Some(operation) = operation {
+        journal::finish(&database, operation)
+            .unwrap_or_else(|err| eprintln!("Failed to finish journal entry: {}", err));
+    }
+    status::Status::current(&database)
+        .map_err(|err| err.to_string())
+        .and_then(|current| status::Status::save(current.as_ref(), logtimedb::state_path(&db_path).as_ref()))
+        .unwrap_or_else(|err| eprintln!("Failed to update the prompt state: {}", err));
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+// The command line without global options, used to describe journal entries
+fn command_line() -> String {
+    let mut words = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--db" | "--fish" | "--zsh" | "--bash" | "--sh" | "--nu" | "--tz" => { args.next(); },
+            _ if ["--db=", "--fish=", "--zsh=", "--bash=", "--sh=", "--nu=", "--tz="].iter().any(|p| arg.starts_with(p)) => {},
+            _ => words.push(arg),
+        }
+    }
+    words.join(" ")
 }
 
-fn run_cmd<A: Iterator<Item=String>, S: shell::Shell>(cmd: &str, args: &mut A, db: &diesel::sqlite::SqliteConnection, shell: &mut S) {
+fn run_cmd<S: shell::Shell>(cmd: &str, args: &clap::ArgMatches, db: &diesel::sqlite::SqliteConnection, db_path: &str, shell: &mut S) -> Result<(), models::DbOrMiscError> {
     match cmd {
         "current" => commands::current(args, db, shell),
+        "status" => commands::status(args, db, shell),
         "start" => commands::start(args, db, shell),
         "stop" => commands::stop(args, db, shell),
+        "pause" => commands::pause(args, db, shell),
+        "resume" => commands::resume(args, db, shell),
         "cd" => commands::cd(args, db, shell),
         "display" => commands::display(args, db, shell),
-        _ => { eprintln!("Unrecognised command!"); },
+        "project" => commands::project(args, db, shell),
+        "subtask" => commands::subtask(args, db, shell),
+        "add" => commands::add(args, db, shell),
+        "log" => commands::log(args, db, shell),
+        "recent" => commands::recent(args, db, shell),
+        "idle" => commands::idle(args, db, db_path, shell),
+        "fix-open" => commands::fix_open(args, db, db_path, shell),
+        "edit" => commands::edit(args, db, shell),
+        "split" => commands::split(args, db, shell),
+        "rm" => commands::rm(args, db, shell),
+        "export" => commands::export(args, db, shell),
+        "import" => commands::import(args, db, shell),
+        "undo" => commands::undo(args, db, shell),
+        "redo" => commands::redo(args, db, shell),
+        _ => unreachable!(),
     }
 }
-
@@ -3,7 +3,7 @@ use std::time::SystemTime;
 use std::collections::HashMap;
 
 use diesel::sqlite::SqliteConnection;
-use diesel::{Connection, QueryDsl, RunQueryDsl,
+use diesel::{Connection, GroupByDsl, QueryDsl, RunQueryDsl,
     ExpressionMethods};
 use diesel::expression_methods::BoolExpressionMethods;
 use chrono::DateTime;
@@ -11,8 +11,9 @@ use chrono::Duration;
 use chrono::naive::NaiveDate;
 use chrono::offset::TimeZone;
 use chrono_tz::Tz;
-use chrono_tz::Pacific::Auckland;
 use super::schema;
+use crate::config;
+use crate::timeexpr::TimeExpr;
 
 #[derive(Queryable)]
 pub struct Project {
@@ -28,21 +29,31 @@ pub struct Stretch {
     pub subtask_id: i64,
     pub start: DateTime<Tz>,
     pub end: Option<DateTime<Tz>>,
+    pub timezone: Option<Tz>,
 }
 
 impl diesel::deserialize::Queryable<super::schema::stretches::SqlType, diesel::sqlite::Sqlite> for Stretch {
-    type Row = (i64, i64, i64, Option<i64>);
+    type Row = (i64, i64, i64, Option<i64>, Option<String>);
 
     fn build(row: Self::Row) -> Self {
         Stretch {
             id: row.0,
             subtask_id: row.1,
             start: current_timezone().timestamp(row.2, 0),
-            end: row.3.map(|ts| current_timezone().timestamp(ts, 0))
+            end: row.3.map(|ts| current_timezone().timestamp(ts, 0)),
+            timezone: row.4.and_then(|tz| Tz::from_str(tz.as_ref()).ok()),
         }
     }
 }
 
+// The zone stretches are shown in: the one they were recorded in, or a
+// single display zone
+#[derive(Clone, Copy)]
+pub enum Zone {
+    Recorded,
+    Fixed(Tz),
+}
+
 #[derive(Queryable)]
 pub struct Subtask {
     pub id: i64,
@@ -95,6 +106,36 @@ pub fn get_project<'a>(conn: &SqliteConnection, code: &'a str) -> Result<Project
 }
 
 impl Project {
+    pub fn all(conn: &SqliteConnection) -> Result<Vec<Self>, diesel::result::Error> {
+        use schema::projects::dsl;
+        dsl::projects.order(dsl::code.asc())
+            .load::<Self>(conn)
+    }
+
+    pub fn by_code(conn: &SqliteConnection, code: &str) -> Result<Self, diesel::result::Error> {
+        use schema::projects::dsl;
+        dsl::projects.filter(dsl::code.eq(code))
+            .get_result::<Self>(conn)
+    }
+
+    pub fn set_directory(&mut self, conn: &SqliteConnection, directory: Option<String>) -> Result<(), diesel::result::Error> {
+        use schema::projects::dsl;
+        diesel::update(dsl::projects.filter(dsl::id.eq(self.id)))
+            .set(dsl::directory.eq(directory.as_ref()))
+            .execute(conn)?;
+        self.directory = directory;
+        Ok(())
+    }
+
+    pub fn set_name(&mut self, conn: &SqliteConnection, name: Option<String>) -> Result<(), diesel::result::Error> {
+        use schema::projects::dsl;
+        diesel::update(dsl::projects.filter(dsl::id.eq(self.id)))
+            .set(dsl::name.eq(name.as_ref()))
+            .execute(conn)?;
+        self.name = name;
+        Ok(())
+    }
+
     pub fn tasks(&self, conn: &SqliteConnection) -> Result<Vec<Task>, diesel::result::Error> {
         use super::schema::tasks::dsl;
         dsl::tasks.filter(dsl::project_id.eq(self.id))
@@ -271,23 +312,136 @@ impl Subtask {
         })
     }
 
+    pub fn load(conn: &SqliteConnection, id: i64) -> Result<Self, diesel::result::Error> {
+        use schema::subtasks::dsl;
+        dsl::subtasks.filter(dsl::id.eq(id))
+            .get_result::<Self>(conn)
+    }
+
+    pub fn set_branch(&mut self, conn: &SqliteConnection, branch: Option<String>) -> Result<(), diesel::result::Error> {
+        use schema::subtasks::dsl;
+        diesel::update(dsl::subtasks.filter(dsl::id.eq(self.id)))
+            .set(dsl::branch.eq(branch.as_ref()))
+            .execute(conn)?;
+        self.branch = branch;
+        Ok(())
+    }
+
+    pub fn set_description(&mut self, conn: &SqliteConnection, description: Option<String>) -> Result<(), diesel::result::Error> {
+        use schema::subtasks::dsl;
+        diesel::update(dsl::subtasks.filter(dsl::id.eq(self.id)))
+            .set(dsl::description.eq(description.as_ref()))
+            .execute(conn)?;
+        self.description = description;
+        Ok(())
+    }
+
+    pub fn add_stretch(&self, conn: &SqliteConnection, start: DateTime<Tz>, end: DateTime<Tz>) -> Result<(), DbOrMiscError> {
+        use schema::stretches;
+        #[derive(Insertable)]
+        #[table_name="stretches"]
+        struct NewStretch<'x> {
+            subtask_id: i64,
+            start: i64,
+            end: i64,
+            timezone: &'x str,
+        }
+        if end <= start {
+            return Err(DbOrMiscError::from("End time must be after start time"));
+        }
+        SqliteConnection::transaction(conn, || {
+            let overlaps = Stretch::overlapping(conn, start, end, None)?;
+            if let Some(other) = overlaps.first() {
+                return Err(DbOrMiscError::from(format!("Overlaps existing stretch {} ({})", other.id, other.span())));
+            }
+            diesel::insert_into(stretches::table)
+                .values(&NewStretch {
+                    subtask_id: self.id,
+                    start: start.timestamp(),
+                    end: end.timestamp(),
+                    timezone: start.timezone().name(),
+                })
+                .execute(conn)
+                .map(|_| ())
+                .map_err(std::convert::From::from)
+        })
+    }
+
     pub fn begin(&self, conn: &SqliteConnection) -> Result<(), DbOrMiscError> {
+        self.begin_at(conn, now())
+    }
+
+    pub fn begin_at(&self, conn: &SqliteConnection, start: DateTime<Tz>) -> Result<(), DbOrMiscError> {
         use schema::stretches;
+        use schema::stretches::dsl;
         #[derive(Insertable)]
         #[table_name="stretches"]
-        struct NewStretch {
+        struct NewStretch<'x> {
             subtask_id: i64,
             start: i64,
+            timezone: &'x str,
         }
+        if let Some(other) = dsl::stretches
+            .filter(dsl::end.gt(start.timestamp()))
+            .order(dsl::start.asc())
+            .load::<Stretch>(conn)?
+            .first() {
+            return Err(DbOrMiscError::from(format!("Overlaps existing stretch {} ({})", other.id, other.span())));
+        }
+        diesel::delete(schema::pauses::dsl::pauses.filter(schema::pauses::dsl::subtask_id.eq(self.id)))
+            .execute(conn)?;
         diesel::insert_into(stretches::table)
             .values(&NewStretch {
                 subtask_id: self.id,
-                start: SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64
+                start: start.timestamp(),
+                timezone: start.timezone().name(),
             })
             .execute(conn)
             .map(|_| ())
             .map_err(std::convert::From::from)
     }
+
+    pub fn pause(&self, conn: &SqliteConnection, at: DateTime<Tz>) -> Result<(), diesel::result::Error> {
+        use schema::pauses;
+        use schema::pauses::dsl;
+        #[derive(Insertable)]
+        #[table_name="pauses"]
+        struct NewPause {
+            subtask_id: i64,
+            paused: i64,
+        }
+        diesel::delete(dsl::pauses.filter(dsl::subtask_id.eq(self.id)))
+            .execute(conn)?;
+        diesel::insert_into(pauses::table)
+            .values(&NewPause {
+                subtask_id: self.id,
+                paused: at.timestamp(),
+            })
+            .execute(conn)
+            .map(|_| ())
+    }
+
+    pub fn last_paused(conn: &SqliteConnection) -> Result<Option<(Project,Task,Subtask)>, diesel::result::Error> {
+        use schema::pauses::dsl;
+        match schema::projects::dsl::projects
+            .inner_join(
+                schema::tasks::dsl::tasks
+                .inner_join(
+                schema::subtasks::dsl::subtasks
+                .inner_join(
+                schema::pauses::dsl::pauses
+                ))
+            )
+            .order((dsl::paused.desc(), dsl::id.desc()))
+            .select((
+                    schema::projects::all_columns,
+                    schema::tasks::all_columns,
+                    schema::subtasks::all_columns,
+            )).first::<(Project,Task,Subtask)>(conn) {
+                Err(diesel::result::Error::NotFound) => Ok(None),
+                result => result.map(Some),
+            }
+    }
 }
 
 impl Stretch {
@@ -298,18 +452,131 @@ impl Stretch {
             .ok()
     }
 
+    pub fn overlapping(conn: &SqliteConnection, start: DateTime<Tz>, end: DateTime<Tz>, exclude: Option<i64>) -> Result<Vec<Self>, diesel::result::Error> {
+        use schema::stretches::dsl;
+        let mut query = dsl::stretches
+            .filter(dsl::start.lt(end.timestamp()))
+            .filter(dsl::end.gt(start.timestamp()).or(dsl::end.is_null()))
+            .order(dsl::start.asc())
+            .into_boxed();
+        if let Some(id) = exclude {
+            query = query.filter(dsl::id.ne(id));
+        }
+        query.load::<Self>(conn)
+    }
+
+    pub fn load(conn: &SqliteConnection, id: i64) -> Result<Self, diesel::result::Error> {
+        use schema::stretches::dsl;
+        dsl::stretches.filter(dsl::id.eq(id))
+            .get_result::<Self>(conn)
+    }
+
+    pub fn update(&mut self, conn: &SqliteConnection, subtask_id: i64, start: DateTime<Tz>, end: Option<DateTime<Tz>>) -> Result<(), DbOrMiscError> {
+        use schema::stretches::dsl;
+        let check_until = match end {
+            Some(end) if end <= start => {
+                return Err(DbOrMiscError::from("End time must be after start time"));
+            },
+            Some(end) => end,
+            None => now(),
+        };
+        SqliteConnection::transaction(conn, || {
+            let overlaps = Self::overlapping(conn, start, check_until, Some(self.id))?;
+            if let Some(other) = overlaps.first() {
+                return Err(DbOrMiscError::from(format!("Overlaps existing stretch {} ({})", other.id, other.span())));
+            }
+            diesel::update(dsl::stretches.filter(dsl::id.eq(self.id)))
+                .set((
+                    dsl::subtask_id.eq(subtask_id),
+                    dsl::start.eq(start.timestamp()),
+                    dsl::end.eq(end.map(|end| end.timestamp())),
+                ))
+                .execute(conn)?;
+            self.subtask_id = subtask_id;
+            self.start = start;
+            self.end = end;
+            Ok(())
+        })
+    }
+
+    pub fn split(&mut self, conn: &SqliteConnection, at: DateTime<Tz>) -> Result<(), DbOrMiscError> {
+        use schema::stretches;
+        use schema::stretches::dsl;
+        #[derive(Insertable)]
+        #[table_name="stretches"]
+        struct NewStretch<'x> {
+            subtask_id: i64,
+            start: i64,
+            end: Option<i64>,
+            timezone: Option<&'x str>,
+        }
+        if at <= self.start || self.end.map_or(false, |end| at >= end) {
+            return Err(DbOrMiscError::from(format!("{} is not within stretch {}", at.format(TIME_FORMAT), self.id)));
+        }
+        SqliteConnection::transaction(conn, || {
+            diesel::update(dsl::stretches.filter(dsl::id.eq(self.id)))
+                .set(dsl::end.eq(at.timestamp()))
+                .execute(conn)?;
+            diesel::insert_into(stretches::table)
+                .values(&NewStretch {
+                    subtask_id: self.subtask_id,
+                    start: at.timestamp(),
+                    end: self.end.map(|end| end.timestamp()),
+                    timezone: self.timezone.map(|tz| tz.name()),
+                })
+                .execute(conn)?;
+            self.end = Some(at);
+            Ok(())
+        })
+    }
+
+    pub fn in_zone(mut self, zone: Zone) -> Self {
+        let tz = match zone {
+            Zone::Recorded => self.timezone.unwrap_or_else(current_timezone),
+            Zone::Fixed(tz) => tz,
+        };
+        self.start = self.start.with_timezone(&tz);
+        self.end = self.end.map(|end| end.with_timezone(&tz));
+        self
+    }
+
+    pub fn delete(self, conn: &SqliteConnection) -> Result<(), diesel::result::Error> {
+        use schema::stretches::dsl;
+        diesel::delete(dsl::stretches.filter(dsl::id.eq(self.id)))
+            .execute(conn)
+            .map(|_| ())
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.end.unwrap_or_else(now) - self.start
+    }
+
+    pub fn span(&self) -> String {
+        match self.end {
+            None => format!("{} - ", self.start.format(TIME_FORMAT)),
+            Some(end) => format!("{} - {}", self.start.format(TIME_FORMAT), end.format(TIME_FORMAT)),
+        }
+    }
+
     pub fn stop_all(conn: &SqliteConnection) -> Result<(), DbOrMiscError> {
         Self::stop_all_at(conn, None)
     }
 
-    pub fn stop_all_at(conn: &SqliteConnection, when: Option<String>) -> Result<(), DbOrMiscError> {
+    pub fn stop_all_at(conn: &SqliteConnection, when: Option<DateTime<Tz>>) -> Result<(), DbOrMiscError> {
         use schema::stretches::dsl;
         let timestamp = match when {
-            None => Ok(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64),
-            Some(timestring) => current_timezone().datetime_from_str(timestring.as_ref(), "%Y-%m%dT%H:%M:%S")
-                .map(|time| time.timestamp())
-                .map_err(|e| e.to_string())
-        }?;
+            None => SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64,
+            Some(time) => time.timestamp(),
+        };
+        if let Some(start) = current_stretch_scope(dsl::stretches)
+            .filter(dsl::start.gt(timestamp))
+            .select(dsl::start)
+            .load::<i64>(conn)?
+            .first() {
+            return Err(DbOrMiscError::from(format!("Cannot stop at {}: the running stretch started at {}",
+                current_timezone().timestamp(timestamp, 0).format(TIME_FORMAT),
+                current_timezone().timestamp(*start, 0).format(TIME_FORMAT))));
+        }
         diesel::update(current_stretch_scope(dsl::stretches))
             .set(dsl::end.eq(timestamp))
             .execute(conn)
@@ -368,6 +635,10 @@ pub fn dates_to_timestamp_ranges<I: IntoIterator<Item=NaiveDate>>(source: I) ->
     })
 }
 
+pub fn now() -> DateTime<Tz> {
+    current_timezone().from_utc_datetime(&chrono::offset::Utc::now().naive_utc())
+}
+
 pub fn today() -> NaiveDate {
     current_timezone()
         .from_utc_datetime(&chrono::offset::Utc::now().naive_utc())
@@ -375,9 +646,166 @@ pub fn today() -> NaiveDate {
         .naive_local()
 }
 
-pub fn time_since(conn: &SqliteConnection, from: NaiveDate) -> Result<HashMap<NaiveDate, HashMap<String, Duration>>, DbOrMiscError> {
+pub fn subtask_code(project: &Project, task: &Task, subtask: &Subtask) -> String {
+    format!("{}-{}-{}", project.code, task.number, subtask.number)
+}
+
+pub fn all_codes(conn: &SqliteConnection) -> Result<Vec<String>, diesel::result::Error> {
+    let mut codes = Vec::new();
+    for project in Project::all(conn)? {
+        codes.push(project.code.clone());
+        let mut tasks = project.tasks(conn)?;
+        tasks.sort_by_key(|task| task.number);
+        for task in tasks {
+            codes.push(format!("{}-{}", project.code, task.number));
+            let mut subtasks = task.subtasks(conn)?;
+            subtasks.sort_by_key(|subtask| subtask.number);
+            for subtask in subtasks {
+                codes.push(subtask_code(&project, &task, &subtask));
+            }
+        }
+    }
+    Ok(codes)
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    format!("{}:{:02}:{:02}",
+            duration.num_hours(),
+            duration.num_minutes() % 60,
+            duration.num_seconds() % 60)
+}
+
+pub fn format_ago(duration: Duration) -> String {
+    let (n, unit) = if duration.num_days() > 0 {
+        (duration.num_days(), "day")
+    } else if duration.num_hours() > 0 {
+        (duration.num_hours(), "hour")
+    } else if duration.num_minutes() > 0 {
+        (duration.num_minutes(), "minute")
+    } else {
+        return String::from("just now");
+    };
+    format!("{} {}{} ago", n, unit, if n == 1 { "" } else { "s" })
+}
+
+pub fn stretches_between(conn: &SqliteConnection, from: NaiveDate, until: Option<NaiveDate>, zone: Zone) -> Result<Vec<(Project,Task,Subtask,Stretch)>, diesel::result::Error> {
+    use schema::stretches::dsl;
+    // Dates are in the display zone of each stretch, so the query allows a
+    // day either side and the stretches are filtered once converted.
+    let early = current_timezone().from_local_datetime(&from.pred().and_hms(0,0,0)).earliest().unwrap();
+    let late = until.map(|until| current_timezone().from_local_datetime(&until.succ().succ().and_hms(0,0,0)).latest().unwrap());
+    let mut query = schema::projects::dsl::projects
+        .inner_join(
+            schema::tasks::dsl::tasks
+            .inner_join(
+            schema::subtasks::dsl::subtasks
+            .inner_join(
+            schema::stretches::dsl::stretches
+            ))
+        )
+        .filter(dsl::end.ge(early.timestamp()).or(dsl::end.is_null()))
+        .order(dsl::start.asc())
+        .select((
+                schema::projects::all_columns,
+                schema::tasks::all_columns,
+                schema::subtasks::all_columns,
+                schema::stretches::all_columns
+        )).into_boxed();
+    if let Some(late) = late {
+        query = query.filter(dsl::start.lt(late.timestamp()));
+    }
+    Ok(query.load::<(Project,Task,Subtask,Stretch)>(conn)?
+       .into_iter()
+       .map(|(project, task, subtask, stretch)| (project, task, subtask, stretch.in_zone(zone)))
+       .filter(|(_, _, _, stretch)| {
+           let tz = stretch.start.timezone();
+           let from = tz.from_local_datetime(&from.and_hms(0,0,0)).earliest().unwrap();
+           let until = until.map(|until| tz.from_local_datetime(&until.succ().and_hms(0,0,0)).latest().unwrap());
+           stretch.end.map_or(true, |end| end >= from) && until.map_or(true, |until| stretch.start < until)
+       })
+       .collect())
+}
+
+// The latest stretch of each of the `limit` most recently started subtasks
+pub fn recent_subtasks(conn: &SqliteConnection, limit: usize) -> Result<Vec<(Project,Task,Subtask,Stretch)>, diesel::result::Error> {
+    use schema::stretches::dsl;
+    // The latest start of each subtask, found by SQLite rather than by
+    // loading the whole history
+    let latest = dsl::stretches
+        .group_by(dsl::subtask_id)
+        .select((dsl::subtask_id, diesel::dsl::sql::<diesel::sql_types::BigInt>("max(start)")))
+        .order(diesel::dsl::sql::<diesel::sql_types::BigInt>("max(start) DESC"))
+        .limit(limit as i64)
+        .load::<(i64, i64)>(conn)?;
+    latest.into_iter()
+        .map(|(subtask_id, start)| schema::projects::dsl::projects
+            .inner_join(
+                schema::tasks::dsl::tasks
+                .inner_join(
+                schema::subtasks::dsl::subtasks
+                .inner_join(
+                schema::stretches::dsl::stretches
+                ))
+            )
+            .filter(dsl::subtask_id.eq(subtask_id))
+            .filter(dsl::start.eq(start))
+            .order(dsl::id.desc())
+            .select((
+                    schema::projects::all_columns,
+                    schema::tasks::all_columns,
+                    schema::subtasks::all_columns,
+                    schema::stretches::all_columns
+            )).first::<(Project,Task,Subtask,Stretch)>(conn))
+        .collect()
+}
+
+pub fn current_stretch(conn: &SqliteConnection) -> Result<Option<(Project,Task,Subtask,Stretch)>, diesel::result::Error> {
+    use schema::stretches::dsl;
+    match current_stretch_scope(schema::projects::dsl::projects
+        .inner_join(
+            schema::tasks::dsl::tasks
+            .inner_join(
+            schema::subtasks::dsl::subtasks
+            .inner_join(
+            schema::stretches::dsl::stretches
+            ))
+        ))
+        .order(dsl::start.desc())
+        .select((
+                schema::projects::all_columns,
+                schema::tasks::all_columns,
+                schema::subtasks::all_columns,
+                schema::stretches::all_columns
+        )).get_result::<(Project,Task,Subtask,Stretch)>(conn) {
+            Err(diesel::result::Error::NotFound) => Ok(None),
+            result => result.map(Some),
+        }
+}
+
+// Running stretches which have been open for longer than `max`
+pub fn overlong_stretches(conn: &SqliteConnection, max: Duration) -> Result<Vec<(Project,Task,Subtask,Stretch)>, diesel::result::Error> {
+    use schema::stretches::dsl;
+    current_stretch_scope(schema::projects::dsl::projects
+        .inner_join(
+            schema::tasks::dsl::tasks
+            .inner_join(
+            schema::subtasks::dsl::subtasks
+            .inner_join(
+            schema::stretches::dsl::stretches
+            ))
+        ))
+        .filter(dsl::start.lt((now() - max).timestamp()))
+        .order(dsl::start.asc())
+        .select((
+                schema::projects::all_columns,
+                schema::tasks::all_columns,
+                schema::subtasks::all_columns,
+                schema::stretches::all_columns
+        )).load::<(Project,Task,Subtask,Stretch)>(conn)
+}
+
+pub fn time_between(conn: &SqliteConnection, from: NaiveDate, until: NaiveDate, zone: Zone) -> Result<HashMap<NaiveDate, HashMap<String, Duration>>, DbOrMiscError> {
     let mut result = HashMap::new();
-    let today = today();
     for (project, task, subtask, stretch) in filter_stretch_date(schema::projects::dsl::projects
         .inner_join(
             schema::tasks::dsl::tasks
@@ -386,18 +814,20 @@ pub fn time_since(conn: &SqliteConnection, from: NaiveDate) -> Result<HashMap<Na
             .inner_join(
             schema::stretches::dsl::stretches
             ))
-        ), from, today)
+        ), from.pred(), until.succ())
         .select((
                 schema::projects::all_columns,
                 schema::tasks::all_columns,
                 schema::subtasks::all_columns,
                 schema::stretches::all_columns
         )).load::<(Project,Task,Subtask,Stretch)>(conn)? {
-        let code = format!("{}-{}-{}", project.code, task.number, subtask.number);
+        let code = subtask_code(&project, &task, &subtask);
+        let stretch = stretch.in_zone(zone);
+        let tz = stretch.start.timezone();
         for date in stretch.dates() {
-            if date >= from && date <= today {
-                let morning = current_timezone().from_local_datetime(&date.and_hms(0,0,0)).earliest().unwrap();
-                let night = current_timezone().from_local_datetime(&date.succ().and_hms(0,0,0)).latest().unwrap();
+            if date >= from && date <= until {
+                let morning = tz.from_local_datetime(&date.and_hms(0,0,0)).earliest().unwrap();
+                let night = tz.from_local_datetime(&date.succ().and_hms(0,0,0)).latest().unwrap();
                 let duration = stretch.time_in_range(morning, night).unwrap();
                 result.entry(date)
                     .or_insert_with(HashMap::new)
@@ -425,13 +855,29 @@ fn filter_stretch_date<S: diesel::query_dsl::methods::FilterDsl<diesel::expressi
     scope.filter(dsl::start.gt(until.timestamp()).or(dsl::end.lt(from.timestamp())).eq(false))
 }
 
-fn current_timezone() -> Tz {
+pub const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+pub fn parse_time(timestring: &str) -> Result<DateTime<Tz>, String> {
+    timestring.parse::<TimeExpr>()?.resolve(now())
+}
+
+pub fn current_timezone() -> Tz {
     match std::env::var("LOGTIME_TZ").ok().and_then(|tzs| Tz::from_str(tzs.as_ref()).ok()) {
-        None => Auckland,
+        None => config::default_timezone(),
         Some(tz) => tz,
     }
 }
 
+impl std::str::FromStr for Zone {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "recorded" => Ok(Zone::Recorded),
+            _ => Tz::from_str(s).map(Zone::Fixed).map_err(|e| e.to_string()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum DbOrMiscError {
     Db(diesel::result::Error),
@@ -1,3 +1,33 @@
+table! {
+    journal (id) {
+        id -> BigInt,
+        operation_id -> BigInt,
+        table_name -> Text,
+        action -> Text,
+        row_id -> BigInt,
+        old_values -> Nullable<Text>,
+        new_values -> Nullable<Text>,
+    }
+}
+
+table! {
+    operations (id) {
+        id -> BigInt,
+        command -> Text,
+        created -> BigInt,
+        state -> Integer,
+        pid -> Nullable<BigInt>,
+    }
+}
+
+table! {
+    pauses (id) {
+        id -> BigInt,
+        subtask_id -> BigInt,
+        paused -> BigInt,
+    }
+}
+
 table! {
     projects (id) {
         id -> BigInt,
@@ -13,6 +43,7 @@ table! {
         subtask_id -> BigInt,
         start -> BigInt,
         end -> Nullable<BigInt>,
+        timezone -> Nullable<Text>,
     }
 }
 
@@ -36,11 +67,16 @@ table! {
     }
 }
 
+joinable!(journal -> operations (operation_id));
+joinable!(pauses -> subtasks (subtask_id));
 joinable!(stretches -> subtasks (subtask_id));
 joinable!(subtasks -> tasks (task_id));
 joinable!(tasks -> projects (project_id));
 
 allow_tables_to_appear_in_same_query!(
+    journal,
+    operations,
+    pauses,
     projects,
     stretches,
     subtasks,
@@ -0,0 +1,136 @@
+use std::io::Write;
+
+pub struct Bash<Output> {
+    output: Output,
+}
+
+impl<Output: Write> super::Shell for Bash<Output> {
+    fn cd(&mut self, path: &std::path::Path) -> Result<(),std::io::Error> {
+        write!(self.output, "cd {}\n", escape_string(path.display().to_string().as_str()))
+    }
+    fn cmd(&mut self, exe: &str, args: &Vec<String>) -> Result<(),std::io::Error> {
+        write!(self.output, "{}\n", std::iter::once(exe)
+               .chain(args.iter().map(|arg| arg.as_ref()))
+               .map(escape_string)
+               .collect::<Vec<_>>()
+               .join(" "))
+    }
+    fn env(&mut self, key: &str, val: &str) -> Result<(),std::io::Error> {
+        write!(self.output, "export {}={}\n", key, escape_string(val))
+    }
+    fn unset_env(&mut self, key: &str) -> Result<(),std::io::Error> {
+        write!(self.output, "unset {}\n", key)
+    }
+}
+
+impl<Output: Write> Bash<Output> {
+    pub fn new(output: Output) -> Self {
+        Bash { output: output }
+    }
+}
+
+// Plain words are left alone, anything with control characters goes in
+// $'...' and everything else in single quotes.
+fn escape_string(raw: &str) -> String {
+    if raw.len() == 0 {
+        return String::from("\'\'");
+    }
+    if raw.chars().all(|i| i.is_ascii_alphanumeric() || "-_./,:+=@".contains(i)) {
+        return String::from(raw);
+    }
+    if !raw.chars().any(|i| i.is_control()) {
+        return format!("\'{}\'", raw.replace('\'', "\'\\\'\'"));
+    }
+    let mut out = String::from("$\'");
+    for i in raw.chars() {
+        match i {
+            '\\' => out.push_str("\\\\"),
+            '\'' => out.push_str("\\\'"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            _ if i.is_control() => {
+                for byte in i.to_string().bytes() {
+                    out.push_str(format!("\\x{:02x}", byte).as_str());
+                }
+            },
+            _ => out.push(i),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use crate::shell::Shell;
+    use super::{Bash, escape_string};
+
+    fn samples() -> Vec<String> {
+        let mut samples = vec![
+            "", "plain", "with space", "it's", "'", "''", "back\\slash", "$HOME", "`date`",
+            "$(date)", "new\nline", "tab\there", "*?[]{}", "-dash", "~user", "#hash",
+            "semi;colon", "pipe|amp&", "ünïcödé", "\u{1b}[0m", "\"quoted\"", "!bang",
+            "it's\nquoted\\", "\r\n", "c1\u{85}control", "\u{7f}",
+        ].into_iter().map(String::from).collect::<Vec<_>>();
+        samples.extend((1u8..128).map(|b| format!("a{}b", b as char)));
+        samples
+    }
+
+    #[test]
+    fn control_characters_use_ansi_c_quoting() {
+        assert_eq!(escape_string("a\nb"), "$'a\\nb'");
+        assert_eq!(escape_string("it's\u{1b}"), "$'it\\'s\\x1b'");
+        assert_eq!(escape_string("c1\u{85}"), "$'c1\\xc2\\x85'");
+        assert_eq!(escape_string("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn escaping_round_trips_through_bash() {
+        for sample in samples() {
+            let output = Command::new("bash")
+                .arg("-c")
+                .arg(format!("printf %s {}", escape_string(sample.as_ref())))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8(output.stdout).unwrap(), sample);
+        }
+    }
+
+    #[test]
+    fn cd_round_trips_arbitrary_paths() {
+        let base = std::env::temp_dir().join(format!("logtime-bash-test-{}", std::process::id()));
+        for (n, sample) in samples().into_iter().filter(|s| !s.contains('/')).enumerate() {
+            let dir = base.join(format!("{}{}", n, sample));
+            std::fs::create_dir_all(&dir).unwrap();
+            let mut script = Vec::new();
+            Bash::new(&mut script).cd(&dir).unwrap();
+            let output = Command::new("bash")
+                .arg("-c")
+                .arg(format!("{}printf %s \"$PWD\"", String::from_utf8(script).unwrap()))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8(output.stdout).unwrap(), dir.display().to_string());
+        }
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn env_and_cmd_round_trip() {
+        for sample in samples() {
+            let mut script = Vec::new();
+            {
+                let mut bash = Bash::new(&mut script);
+                bash.env("LOGTIME_TEST", sample.as_ref()).unwrap();
+                bash.cmd("printf", &vec![String::from("%s|%s"), sample.clone(), String::from("$LOGTIME_TEST")]).unwrap();
+            }
+            let output = Command::new("bash")
+                .arg("-c")
+                .arg(format!("{}printf %s \"$LOGTIME_TEST\"", String::from_utf8(script).unwrap()))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8(output.stdout).unwrap(), format!("{}|$LOGTIME_TEST{}", sample, sample));
+        }
+    }
+}
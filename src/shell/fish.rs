@@ -16,7 +16,10 @@ impl<Output: Write> super::Shell for Fish<Output> {
                .join(" "))
     }
     fn env(&mut self, key: &str, val: &str) -> Result<(),std::io::Error> {
-        write!(self.output, "set {} {}\n", escape_string(key), escape_string(val))
+        write!(self.output, "set -gx {} {}\n", escape_string(key), escape_string(val))
+    }
+    fn unset_env(&mut self, key: &str) -> Result<(),std::io::Error> {
+        write!(self.output, "set -eg {}\n", escape_string(key))
     }
 }
 
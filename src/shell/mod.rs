@@ -1,5 +1,8 @@
 pub mod fish;
 pub mod zsh;
+pub mod bash;
+pub mod sh;
+pub mod nushell;
 
 use std::ops::IndexMut;
 
@@ -7,6 +10,7 @@ pub trait Shell {
     fn cd(&mut self, path: &std::path::Path) -> Result<(),std::io::Error>;
     fn cmd(&mut self, exe: &str, args: &Vec<String>) -> Result<(),std::io::Error>;
     fn env(&mut self, key: &str, val: &str) -> Result<(),std::io::Error>;
+    fn unset_env(&mut self, key: &str) -> Result<(),std::io::Error>;
     fn checkout(&mut self, branch: &str) -> Result<(),std::io::Error> {
         self.cmd("git", &vec![String::from("checkout"), String::from(branch)])
     }
@@ -54,6 +58,16 @@ impl<S: Shell> Shell for Vec<S> {
        }
        Ok(())
    }
+   fn unset_env(&mut self, key: &str) -> Result<(),std::io::Error> {
+       let mut i = 0;
+       while i != self.len() {
+           match self.index_mut(i).unset_env(key) {
+               Ok(_) => { i += 1 },
+               Err(_) => { self.remove(i); },
+           }
+       }
+       Ok(())
+   }
 }
 
 impl Shell for Box<dyn Shell> {
@@ -66,4 +80,7 @@ impl Shell for Box<dyn Shell> {
     fn env(&mut self, key: &str, val: &str) -> Result<(),std::io::Error> {
         self.as_mut().env(key, val)
     }
+    fn unset_env(&mut self, key: &str) -> Result<(),std::io::Error> {
+        self.as_mut().unset_env(key)
+    }
 }
@@ -0,0 +1,178 @@
+use std::io::Write;
+
+// Nushell cannot source a file generated at runtime, so instead of commands
+// we write one NUON record per line for the wrapper to apply.
+pub struct Nushell<Output> {
+    output: Output,
+}
+
+impl<Output: Write> super::Shell for Nushell<Output> {
+    fn cd(&mut self, path: &std::path::Path) -> Result<(),std::io::Error> {
+        // NUON strings are UTF-8, so any other path could only be mangled
+        let path = path.to_str().ok_or_else(|| std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Nushell cannot change to {}, which is not valid UTF-8", path.display())))?;
+        write!(self.output, "{{cd: {}}}\n", escape_string(path))
+    }
+    fn cmd(&mut self, exe: &str, args: &Vec<String>) -> Result<(),std::io::Error> {
+        write!(self.output, "{{run: [{}]}}\n", std::iter::once(exe)
+               .chain(args.iter().map(|arg| arg.as_ref()))
+               .map(escape_string)
+               .collect::<Vec<_>>()
+               .join(", "))
+    }
+    fn env(&mut self, key: &str, val: &str) -> Result<(),std::io::Error> {
+        write!(self.output, "{{env: {}, value: {}}}\n", escape_string(key), escape_string(val))
+    }
+    fn unset_env(&mut self, key: &str) -> Result<(),std::io::Error> {
+        write!(self.output, "{{unset: {}}}\n", escape_string(key))
+    }
+}
+
+impl<Output: Write> Nushell<Output> {
+    pub fn new(output: Output) -> Self {
+        Nushell { output: output }
+    }
+}
+
+// Double quoted strings are never interpolated in nushell (that needs $"..."),
+// so only backslashes, quotes and control characters need escaping.
+fn escape_string(raw: &str) -> String {
+    let mut out = String::from("\"");
+    for i in raw.chars() {
+        match i {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            _ if i.is_control() => out.push_str(format!("\\u{{{:x}}}", i as u32).as_str()),
+            _ => out.push(i),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use crate::shell::Shell;
+    use super::{Nushell, escape_string};
+
+    fn samples() -> Vec<String> {
+        let mut samples = vec![
+            "", "plain", "with space", "it's", "\"", "back\\slash", "$HOME", "$\"(date)\"",
+            "(date)", "new\nline", "tab\there", "{a: b}", "[1, 2]", "#hash", "ünïcödé",
+            "\u{1b}[0m", "\u{7f}", "\u{85}",
+        ].into_iter().map(String::from).collect::<Vec<_>>();
+        samples.extend((1u8..128).map(|b| format!("a{}b", b as char)));
+        samples
+    }
+
+    // Reads back one nushell double quoted string, returning it and the rest
+    // of the input.
+    fn unescape(quoted: &str) -> (String, &str) {
+        let mut chars = quoted.char_indices();
+        assert_eq!(chars.next(), Some((0, '"')));
+        let mut out = String::new();
+        while let Some((n, i)) = chars.next() {
+            match i {
+                '"' => return (out, &quoted[n + 1..]),
+                '\\' => match chars.next().unwrap().1 {
+                    'n' => out.push('\n'),
+                    't' => out.push('\t'),
+                    'r' => out.push('\r'),
+                    'u' => {
+                        assert_eq!(chars.next().unwrap().1, '{');
+                        let hex = chars.by_ref().map(|(_, c)| c).take_while(|c| *c != '}').collect::<String>();
+                        out.push(std::char::from_u32(u32::from_str_radix(hex.as_ref(), 16).unwrap()).unwrap());
+                    },
+                    escaped => out.push(escaped),
+                },
+                _ => {
+                    assert!(!i.is_control(), "unescaped control character in {:?}", quoted);
+                    out.push(i);
+                },
+            }
+        }
+        panic!("unterminated string {:?}", quoted)
+    }
+
+    #[test]
+    fn escaping_round_trips() {
+        for sample in samples() {
+            let escaped = escape_string(sample.as_ref());
+            let (unescaped, rest) = unescape(escaped.as_ref());
+            assert_eq!(unescaped, sample);
+            assert_eq!(rest, "");
+        }
+    }
+
+    #[test]
+    fn records_round_trip_arbitrary_paths() {
+        for sample in samples() {
+            let path = std::path::Path::new("/tmp").join(&sample);
+            let mut script = Vec::new();
+            Nushell::new(&mut script).cd(&path).unwrap();
+            let script = String::from_utf8(script).unwrap();
+            assert_eq!(script.lines().count(), 1);
+            let (unescaped, rest) = unescape(script.trim_start_matches("{cd: "));
+            assert_eq!(unescaped, path.to_str().unwrap());
+            assert_eq!(rest, "}\n");
+        }
+    }
+
+    // Parses each line with nushell's own `from nuon`
+    fn from_nuon(lines: &str) -> serde_json::Value {
+        let output = Command::new("nu")
+            .arg("-c")
+            .arg("$env.LOGTIME_TEST | lines | each {|line| $line | from nuon } | to json --raw")
+            .env("LOGTIME_TEST", lines)
+            .output()
+            .expect("nu must be installed to run this test");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice(&output.stdout).unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs nu; run with --ignored"]
+    fn escaping_round_trips_through_nu() {
+        let escaped = samples().iter().map(|sample| escape_string(sample.as_ref())).collect::<Vec<_>>();
+        assert_eq!(from_nuon(escaped.join("\n").as_ref()), serde_json::json!(samples()));
+    }
+
+    #[test]
+    #[ignore = "needs nu; run with --ignored"]
+    fn records_round_trip_through_nu() {
+        let mut script = Vec::new();
+        let mut expected = Vec::new();
+        {
+            let mut nu = Nushell::new(&mut script);
+            for sample in samples() {
+                let path = std::path::Path::new("/tmp").join(&sample);
+                nu.cd(&path).unwrap();
+                nu.cmd(sample.as_ref(), &vec![sample.clone(), String::from("x")]).unwrap();
+                nu.env("LOGTIME_TEST", sample.as_ref()).unwrap();
+                nu.unset_env(sample.as_ref()).unwrap();
+                expected.push(serde_json::json!({"cd": path.to_str().unwrap()}));
+                expected.push(serde_json::json!({"run": [sample, sample, "x"]}));
+                expected.push(serde_json::json!({"env": "LOGTIME_TEST", "value": sample}));
+                expected.push(serde_json::json!({"unset": sample}));
+            }
+        }
+        let script = String::from_utf8(script).unwrap();
+        assert_eq!(script.lines().count(), expected.len());
+        assert_eq!(from_nuon(script.as_ref()), serde_json::Value::Array(expected));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_paths_that_are_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+        let path = std::path::Path::new(std::ffi::OsStr::from_bytes(b"/tmp/a\xffb"));
+        let mut script = Vec::new();
+        assert!(Nushell::new(&mut script).cd(path).is_err());
+        assert!(script.is_empty());
+    }
+}
@@ -16,7 +16,10 @@ impl<Output: Write> super::Shell for Zsh<Output> {
                .join(" "))
     }
     fn env(&mut self, key: &str, val: &str) -> Result<(),std::io::Error> {
-        write!(self.output, "{}={}\n", escape_string(key), escape_string(val))
+        write!(self.output, "export {}={}\n", escape_string(key), escape_string(val))
+    }
+    fn unset_env(&mut self, key: &str) -> Result<(),std::io::Error> {
+        write!(self.output, "unset {}\n", escape_string(key))
     }
 }
 
@@ -0,0 +1,180 @@
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::offset::TimeZone;
+use chrono_tz::Tz;
+use diesel::sqlite::SqliteConnection;
+use serde::{Serialize, Deserialize};
+use crate::logtimedb;
+use crate::models;
+
+pub struct Status {
+    pub code: String,
+    pub project: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub branch: Option<String>,
+    pub started: DateTime<Tz>,
+    // Time spent on the task earlier on the day the current stretch started
+    pub earlier: Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+struct State {
+    code: String,
+    project: String,
+    name: Option<String>,
+    description: Option<String>,
+    branch: Option<String>,
+    started: i64,
+    earlier: i64,
+}
+
+pub const PLACEHOLDERS: &[&str] = &["code", "project", "name", "description", "branch", "started", "elapsed", "today"];
+
+impl Status {
+    pub fn current(conn: &SqliteConnection) -> Result<Option<Self>, models::DbOrMiscError> {
+        let (project, task, subtask, stretch) = match models::current_stretch(conn)? {
+            None => return Ok(None),
+            Some(current) => current,
+        };
+        let day = stretch.start.date().naive_local();
+        let task_prefix = format!("{}-{}-", project.code, task.number);
+        let earlier = models::time_between(conn, day, day, models::Zone::Fixed(models::current_timezone()))?
+            .values()
+            .flat_map(|entries| entries.iter())
+            .filter(|(code, _)| code.starts_with(task_prefix.as_str()))
+            .fold(Duration::zero(), |total, (_, duration)| total + *duration);
+        Ok(Some(Status {
+            code: models::subtask_code(&project, &task, &subtask),
+            project: project.code,
+            name: project.name,
+            description: subtask.description,
+            branch: subtask.branch,
+            started: stretch.start,
+            earlier: earlier,
+        }))
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Option<Self>, String> {
+        let contents = std::fs::read(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let state = serde_json::from_slice::<Option<State>>(&contents)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Ok(state.map(|state| Status {
+            code: state.code,
+            project: state.project,
+            name: state.name,
+            description: state.description,
+            branch: state.branch,
+            started: models::current_timezone().timestamp(state.started, 0),
+            earlier: Duration::seconds(state.earlier),
+        }))
+    }
+
+    pub fn save(status: Option<&Self>, path: &std::path::Path) -> Result<(), String> {
+        let state = status.map(|status| State {
+            code: status.code.clone(),
+            project: status.project.clone(),
+            name: status.name.clone(),
+            description: status.description.clone(),
+            branch: status.branch.clone(),
+            started: status.started.timestamp(),
+            earlier: status.earlier.num_seconds(),
+        });
+        let contents = serde_json::to_vec(&state).map_err(|e| e.to_string())?;
+        logtimedb::write_file(path, contents.as_ref())
+    }
+
+    pub fn elapsed(&self, now: DateTime<Tz>) -> Duration {
+        now - self.started
+    }
+
+    // Total for the task today, including the current stretch
+    pub fn today(&self, now: DateTime<Tz>) -> Duration {
+        let tz = now.timezone();
+        let midnight = tz.from_local_datetime(&now.date().naive_local().and_hms(0,0,0))
+            .earliest()
+            .unwrap();
+        if self.started >= midnight {
+            self.earlier + (now - self.started)
+        } else {
+            now - midnight
+        }
+    }
+
+    // `{{` and `}}` stand for literal braces, as zsh needs for `%{...%}`
+    pub fn render(&self, template: &str, now: DateTime<Tz>) -> Result<String, String> {
+        let mut out = String::new();
+        let mut rest = template;
+        while let Some(open) = rest.find(|c| c == '{' || c == '}') {
+            out.push_str(&rest[..open]);
+            if rest[open..].starts_with("{{") || rest[open..].starts_with("}}") {
+                out.push_str(&rest[open..open + 1]);
+                rest = &rest[open + 2..];
+                continue;
+            }
+            if rest[open..].starts_with('}') {
+                return Err(format!("Unmatched }} in {:?}; write }}}} for a literal brace", template));
+            }
+            let close = rest[open..].find('}')
+                .ok_or_else(|| format!("Unclosed placeholder in {:?}", template))?;
+            let value = match &rest[open + 1..open + close] {
+                "code" => self.code.clone(),
+                "project" => self.project.clone(),
+                "name" => self.name.clone().unwrap_or_default(),
+                "description" => self.description.clone().unwrap_or_default(),
+                "branch" => self.branch.clone().unwrap_or_default(),
+                "started" => self.started.with_timezone(&now.timezone()).format(models::TIME_FORMAT).to_string(),
+                "elapsed" => models::format_duration(self.elapsed(now)),
+                "today" => models::format_duration(self.today(now)),
+                other => return Err(format!("Unknown placeholder {{{}}}; expected one of {}", other, PLACEHOLDERS.join(", "))),
+            };
+            out.push_str(value.as_ref());
+            rest = &rest[open + close + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Europe::Berlin;
+
+    fn status() -> Status {
+        Status {
+            code: String::from("ABC-1-2"),
+            project: String::from("ABC"),
+            name: Some(String::from("Project")),
+            description: None,
+            branch: Some(String::from("main")),
+            started: Berlin.ymd(2026, 10, 18).and_hms(9, 0, 0),
+            earlier: Duration::minutes(30),
+        }
+    }
+
+    fn render(template: &str) -> Result<String, String> {
+        status().render(template, Berlin.ymd(2026, 10, 18).and_hms(10, 15, 0))
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(render("{code} on {branch}{description}").unwrap(), "ABC-1-2 on main");
+        assert_eq!(render("[{project}] {elapsed}").unwrap(), format!("[ABC] {}", models::format_duration(Duration::minutes(75))));
+    }
+
+    #[test]
+    fn escaped_braces() {
+        assert_eq!(render("%{{$fg[red]%}}{code}%{{$reset_color%}}").unwrap(), "%{$fg[red]%}ABC-1-2%{$reset_color%}");
+        assert_eq!(render("{{code}}").unwrap(), "{code}");
+        assert_eq!(render("{{{code}}}").unwrap(), "{ABC-1-2}");
+    }
+
+    #[test]
+    fn invalid() {
+        for template in ["{code", "{unknown}", "code}", "{}"] {
+            assert!(render(template).is_err(), "{:?} rendered", template);
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, FixedOffset};
+use chrono::naive::{NaiveDate, NaiveTime};
+use chrono::offset::TimeZone;
+use chrono_tz::Tz;
+
+// A time as typed on the command line. Relative forms stay unresolved
+// until the command runs so they are evaluated in the zone in effect then.
+//
+//   now
+//   -15m, -1h30m, -2d          before now
+//   14:30, 9:15:00             today
+//   yesterday 17:00, today     midnight when no time is given
+//   2026-10-18 09:00, 2026-10-18T09:00
+//   2026-10-18T09:00:00+02:00  exact
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeExpr {
+    Now,
+    Ago(Duration),
+    Exact(DateTime<FixedOffset>),
+    Local(Day, Option<NaiveTime>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Day {
+    Today,
+    Yesterday,
+    Date(NaiveDate),
+}
+
+impl TimeExpr {
+    pub fn resolve(&self, now: DateTime<Tz>) -> Result<DateTime<Tz>, String> {
+        let tz = now.timezone();
+        match *self {
+            TimeExpr::Now => Ok(now),
+            TimeExpr::Ago(duration) => Ok(now - duration),
+            TimeExpr::Exact(time) => Ok(time.with_timezone(&tz)),
+            TimeExpr::Local(day, time) => {
+                let naive = day.date(now).and_time(time.unwrap_or_else(|| NaiveTime::from_hms(0, 0, 0)));
+                tz.from_local_datetime(&naive)
+                    .earliest()
+                    .ok_or_else(|| format!("{} does not exist in {}", naive, tz))
+            },
+        }
+    }
+
+    pub fn date(&self, now: DateTime<Tz>) -> Result<NaiveDate, String> {
+        match *self {
+            TimeExpr::Local(day, _) => Ok(day.date(now)),
+            _ => self.resolve(now).map(|time| time.date().naive_local()),
+        }
+    }
+}
+
+impl Day {
+    fn date(&self, now: DateTime<Tz>) -> NaiveDate {
+        match *self {
+            Day::Today => now.date().naive_local(),
+            Day::Yesterday => now.date().naive_local().pred(),
+            Day::Date(date) => date,
+        }
+    }
+}
+
+impl FromStr for TimeExpr {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let unrecognised = || format!("Unrecognised time: {}", s);
+        if s == "now" {
+            return Ok(TimeExpr::Now);
+        }
+        if let Some(ago) = s.strip_prefix('-') {
+            return parse_duration(ago).map(TimeExpr::Ago).ok_or_else(unrecognised);
+        }
+        if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+            return Ok(TimeExpr::Exact(time));
+        }
+        let (day, time) = match s.find(|c: char| c == ' ' || c == 'T') {
+            Some(i) => (&s[..i], Some(s[i + 1..].trim_start())),
+            None if s.contains(':') => ("today", Some(s)),
+            None => (s, None),
+        };
+        let day = match day {
+            "today" => Day::Today,
+            "yesterday" => Day::Yesterday,
+            _ => Day::Date(NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|_| unrecognised())?),
+        };
+        let time = time
+            .map(|time| ["%H:%M:%S", "%H:%M"].iter()
+                 .filter_map(|format| NaiveTime::parse_from_str(time, format).ok())
+                 .next()
+                 .ok_or_else(unrecognised))
+            .transpose()?;
+        Ok(TimeExpr::Local(day, time))
+    }
+}
+
+// 1h30m, 15m, 2d, 90s
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let mut total = Duration::zero();
+    let mut digits = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let n = digits.parse::<i64>().ok()?;
+        digits.clear();
+        total = total + match c {
+            'd' => Duration::days(n),
+            'h' => Duration::hours(n),
+            'm' => Duration::minutes(n),
+            's' => Duration::seconds(n),
+            _ => return None,
+        };
+    }
+    if s.is_empty() || !digits.is_empty() {
+        return None;
+    }
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Europe::Berlin;
+
+    fn now() -> DateTime<Tz> {
+        Berlin.ymd(2026, 10, 18).and_hms(10, 0, 0)
+    }
+
+    fn resolve(s: &str) -> DateTime<Tz> {
+        s.parse::<TimeExpr>().unwrap().resolve(now()).unwrap()
+    }
+
+    #[test]
+    fn relative() {
+        assert_eq!(resolve("now"), now());
+        assert_eq!(resolve("-15m"), Berlin.ymd(2026, 10, 18).and_hms(9, 45, 0));
+        assert_eq!(resolve("-1h30m"), Berlin.ymd(2026, 10, 18).and_hms(8, 30, 0));
+        assert_eq!(resolve("-2d"), Berlin.ymd(2026, 10, 16).and_hms(10, 0, 0));
+    }
+
+    #[test]
+    fn local() {
+        assert_eq!(resolve("14:30"), Berlin.ymd(2026, 10, 18).and_hms(14, 30, 0));
+        assert_eq!(resolve("9:15"), Berlin.ymd(2026, 10, 18).and_hms(9, 15, 0));
+        assert_eq!(resolve("yesterday 17:00"), Berlin.ymd(2026, 10, 17).and_hms(17, 0, 0));
+        assert_eq!(resolve("2026-10-18 09:00"), Berlin.ymd(2026, 10, 18).and_hms(9, 0, 0));
+        assert_eq!(resolve("2026-10-18T09:00:30"), Berlin.ymd(2026, 10, 18).and_hms(9, 0, 30));
+        assert_eq!(resolve("yesterday"), Berlin.ymd(2026, 10, 17).and_hms(0, 0, 0));
+    }
+
+    #[test]
+    fn exact() {
+        assert_eq!(resolve("2026-10-18T09:00:00+13:00"), Berlin.ymd(2026, 10, 17).and_hms(22, 0, 0));
+        assert_eq!(resolve("2026-10-18T07:00:00Z"), Berlin.ymd(2026, 10, 18).and_hms(9, 0, 0));
+    }
+
+    #[test]
+    fn dates() {
+        let date = |s: &str| s.parse::<TimeExpr>().unwrap().date(now()).unwrap();
+        assert_eq!(date("today"), NaiveDate::from_ymd(2026, 10, 18));
+        assert_eq!(date("-3d"), NaiveDate::from_ymd(2026, 10, 15));
+        assert_eq!(date("2026-10-01"), NaiveDate::from_ymd(2026, 10, 1));
+    }
+
+    #[test]
+    fn invalid() {
+        for s in ["", "-", "-15", "-15x", "soon", "25:00", "2026-13-01", "tomorrow 9:00"] {
+            assert!(s.parse::<TimeExpr>().is_err(), "{:?} parsed", s);
+        }
+    }
+}